@@ -0,0 +1,685 @@
+use crate::context::{Context, PlanId, PlanPhase, Plugin};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::any::{type_name, Any};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::rc::Rc;
+
+// A plugin whose data container can be saved in a checkpoint
+pub trait CheckpointPlugin: Plugin {
+    type State: Serialize + DeserializeOwned;
+
+    fn save_state(data_container: &Self::DataContainer) -> Self::State;
+
+    fn load_state(data_container: &mut Self::DataContainer, state: Self::State);
+}
+
+// A plan whose data can be saved in a checkpoint and used to rebuild
+// the plan's callback when the checkpoint is loaded
+pub trait CheckpointPlan: Serialize + DeserializeOwned + 'static {
+    fn execute(self, context: &mut Context);
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Serialization(serde_json::Error),
+    // A section of the checkpoint could not be read
+    InvalidSection(String, serde_json::Error),
+    // The checkpoint has a section that hasn't been registered
+    UnknownSection(String),
+    // A registered section is missing from the checkpoint
+    MissingSection(String),
+    // The checkpoint has a plan type that hasn't been registered
+    UnknownPlan(String),
+    // Checkpoints can't be created while immediate callbacks are queued
+    PendingCallbacks,
+    // The number of pending plans, such as closures and periodic plans,
+    // which aren't checkpoint plans and couldn't be saved
+    UnsavedPlans(usize),
+    // Stored data, such as person properties, global properties, group
+    // types, and rngs, which has no registered section and couldn't be saved
+    UnregisteredState(Vec<String>),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "Checkpoint IO error: {}", e),
+            CheckpointError::Serialization(e) => write!(f, "Checkpoint serialization error: {}", e),
+            CheckpointError::InvalidSection(name, e) => {
+                write!(f, "Invalid checkpoint section {}: {}", name, e)
+            }
+            CheckpointError::UnknownSection(name) => {
+                write!(f, "Checkpoint section {} has not been registered", name)
+            }
+            CheckpointError::MissingSection(name) => {
+                write!(f, "Checkpoint is missing section {}", name)
+            }
+            CheckpointError::UnknownPlan(name) => {
+                write!(f, "Checkpoint plan type {} has not been registered", name)
+            }
+            CheckpointError::PendingCallbacks => {
+                write!(f, "Cannot create a checkpoint while callbacks are queued")
+            }
+            CheckpointError::UnregisteredState(names) => write!(
+                f,
+                "Cannot create a checkpoint with state which hasn't been registered: {}",
+                names.join(", ")
+            ),
+            CheckpointError::UnsavedPlans(count) => write!(
+                f,
+                "Cannot create a checkpoint with {} pending plans which aren't checkpoint plans",
+                count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError {
+    fn from(e: serde_json::Error) -> Self {
+        CheckpointError::Serialization(e)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct CheckpointPlanEntry {
+    time: f64,
+    phase: PlanPhase,
+    name: String,
+    data: Value,
+}
+
+// The saved state of a simulation
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Checkpoint {
+    time: f64,
+    sections: BTreeMap<String, Value>,
+    plans: Vec<CheckpointPlanEntry>,
+}
+
+impl Checkpoint {
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SavedPlan {
+    name: &'static str,
+    data: Value,
+}
+
+type SaveSection = dyn Fn(&Context) -> Result<Value, serde_json::Error>;
+type ParseSection = dyn Fn(Value) -> Result<Box<dyn Any>, serde_json::Error>;
+type ApplySection = dyn Fn(&mut Context, Box<dyn Any>);
+type ContextCallback = dyn FnOnce(&mut Context);
+type PlanLoader = dyn Fn(Value) -> Result<(SavedPlan, Box<ContextCallback>), serde_json::Error>;
+
+#[derive(Clone)]
+pub(crate) struct CheckpointSection {
+    name: String,
+    save: Rc<SaveSection>,
+    parse: Rc<ParseSection>,
+    apply: Rc<ApplySection>,
+}
+
+impl CheckpointSection {
+    pub(crate) fn new<S: Serialize + DeserializeOwned + 'static>(
+        name: String,
+        save: impl Fn(&Context) -> S + 'static,
+        load: impl Fn(&mut Context, S) + 'static,
+    ) -> CheckpointSection {
+        CheckpointSection {
+            name,
+            save: Rc::new(move |context| serde_json::to_value(save(context))),
+            parse: Rc::new(|value| {
+                let state: S = serde_json::from_value(value)?;
+                Ok(Box::new(state))
+            }),
+            apply: Rc::new(move |context, state| load(context, *state.downcast::<S>().unwrap())),
+        }
+    }
+
+    pub(crate) fn for_plugin<T: CheckpointPlugin>() -> CheckpointSection {
+        CheckpointSection::new(
+            type_name::<T>().to_string(),
+            |context| match context.get_data_container::<T>() {
+                Some(data_container) => T::save_state(data_container),
+                None => T::save_state(&T::get_data_container()),
+            },
+            |context, state| T::load_state(context.get_or_init_data_container::<T>(), state),
+        )
+    }
+}
+
+struct CheckpointDataContainer {
+    sections: Vec<CheckpointSection>,
+    plan_loaders: HashMap<String, Rc<PlanLoader>>,
+}
+
+fn get_builtin_sections() -> Vec<CheckpointSection> {
+    vec![
+        crate::people::get_checkpoint_section(),
+        crate::regions::get_checkpoint_section(),
+        crate::random::get_checkpoint_section(),
+    ]
+}
+
+crate::context::define_plugin!(
+    CheckpointRegistryPlugin,
+    CheckpointDataContainer,
+    CheckpointDataContainer {
+        sections: get_builtin_sections(),
+        plan_loaders: HashMap::new(),
+    }
+);
+
+// Returns the names of stored data which has no registered section, in
+// sorted order
+fn get_unregistered_state(context: &Context) -> Vec<String> {
+    let sections = get_sections(context);
+    let mut names: Vec<String> = [
+        crate::person_properties::get_required_checkpoint_sections(context),
+        crate::global_properties::get_required_checkpoint_sections(context),
+        crate::groups::get_required_checkpoint_sections(context),
+        crate::regions::get_required_checkpoint_sections(context),
+        crate::random::get_required_checkpoint_sections(context),
+    ]
+    .concat()
+    .into_iter()
+    .filter(|name| !sections.iter().any(|section| section.name == *name))
+    .map(str::to_string)
+    .collect();
+    names.sort();
+    names
+}
+
+fn get_sections(context: &Context) -> Vec<CheckpointSection> {
+    match context.get_data_container::<CheckpointRegistryPlugin>() {
+        None => get_builtin_sections(),
+        Some(data_container) => data_container.sections.clone(),
+    }
+}
+
+pub trait CheckpointContext {
+    // Adds a named section to checkpoints. Registering a section with the
+    // same name again replaces it.
+    fn add_checkpoint_section<S: Serialize + DeserializeOwned + 'static>(
+        &mut self,
+        name: &str,
+        save: impl Fn(&Context) -> S + 'static,
+        load: impl Fn(&mut Context, S) + 'static,
+    );
+
+    fn register_checkpoint_plugin<T: CheckpointPlugin>(&mut self);
+
+    fn register_checkpoint_plan<T: CheckpointPlan>(&mut self);
+
+    fn add_checkpoint_plan<T: CheckpointPlan>(&mut self, time: f64, plan: T) -> PlanId;
+
+    // Fails rather than leaving out pending plans which aren't checkpoint
+    // plans or stored person, region, and global properties, groups, and
+    // rngs which aren't registered for checkpoints
+    fn create_checkpoint(&self) -> Result<Checkpoint, CheckpointError>;
+
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError>;
+
+    fn save_checkpoint(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError>;
+
+    fn load_checkpoint(&mut self, path: impl AsRef<Path>) -> Result<(), CheckpointError>;
+
+    // Creates a new Context with the same checkpointed state. Callbacks can't
+    // be copied, so setup must make the same registrations (checkpoint
+    // sections, partitions, observers, components) as this Context. Fails
+    // in the same cases as create_checkpoint. Data containers of other
+    // plugins are only copied if registered with register_checkpoint_plugin.
+    fn fork(&self, setup: impl FnOnce(&mut Context)) -> Result<Context, CheckpointError>;
+}
+
+trait InternalCheckpointContext {
+    fn add_checkpoint_section_internal(&mut self, section: CheckpointSection);
+}
+
+impl InternalCheckpointContext for Context {
+    fn add_checkpoint_section_internal(&mut self, section: CheckpointSection) {
+        let data_container = self.get_or_init_data_container::<CheckpointRegistryPlugin>();
+        match data_container
+            .sections
+            .iter_mut()
+            .find(|existing_section| existing_section.name == section.name)
+        {
+            Some(existing_section) => *existing_section = section,
+            None => data_container.sections.push(section),
+        }
+    }
+}
+
+impl CheckpointContext for Context {
+    fn add_checkpoint_section<S: Serialize + DeserializeOwned + 'static>(
+        &mut self,
+        name: &str,
+        save: impl Fn(&Context) -> S + 'static,
+        load: impl Fn(&mut Context, S) + 'static,
+    ) {
+        self.add_checkpoint_section_internal(CheckpointSection::new(name.to_string(), save, load));
+    }
+
+    fn register_checkpoint_plugin<T: CheckpointPlugin>(&mut self) {
+        self.add_checkpoint_section_internal(CheckpointSection::for_plugin::<T>());
+    }
+
+    fn register_checkpoint_plan<T: CheckpointPlan>(&mut self) {
+        let data_container = self.get_or_init_data_container::<CheckpointRegistryPlugin>();
+        data_container.plan_loaders.insert(
+            type_name::<T>().to_string(),
+            Rc::new(|data| {
+                let plan: T = serde_json::from_value(data.clone())?;
+                let saved_plan = SavedPlan {
+                    name: type_name::<T>(),
+                    data,
+                };
+                Ok((saved_plan, Box::new(move |context| plan.execute(context))))
+            }),
+        );
+    }
+
+    fn add_checkpoint_plan<T: CheckpointPlan>(&mut self, time: f64, plan: T) -> PlanId {
+        self.register_checkpoint_plan::<T>();
+        let saved_plan = SavedPlan {
+            name: type_name::<T>(),
+            data: serde_json::to_value(&plan).expect("Failed to serialize plan"),
+        };
+        match self.add_saved_plan(time, PlanPhase::Normal, saved_plan, move |context| {
+            plan.execute(context)
+        }) {
+            Ok(plan_id) => plan_id,
+            Err(e) => panic!("Invalid plan time: {}", e),
+        }
+    }
+
+    fn create_checkpoint(&self) -> Result<Checkpoint, CheckpointError> {
+        if self.has_queued_callbacks() {
+            return Err(CheckpointError::PendingCallbacks);
+        }
+        let unsaved_plan_count = self.unsaved_plan_count();
+        if unsaved_plan_count > 0 {
+            return Err(CheckpointError::UnsavedPlans(unsaved_plan_count));
+        }
+        let unregistered_state = get_unregistered_state(self);
+        if !unregistered_state.is_empty() {
+            return Err(CheckpointError::UnregisteredState(unregistered_state));
+        }
+        let mut sections = BTreeMap::new();
+        for section in get_sections(self) {
+            sections.insert(section.name.clone(), (section.save)(self)?);
+        }
+        let plans = self
+            .get_saved_plans()
+            .into_iter()
+            .map(|(time, phase, saved_plan)| CheckpointPlanEntry {
+                time,
+                phase,
+                name: saved_plan.name.to_string(),
+                data: saved_plan.data.clone(),
+            })
+            .collect();
+        Ok(Checkpoint {
+            time: self.get_time(),
+            sections,
+            plans,
+        })
+    }
+
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        let sections = get_sections(self);
+        for name in checkpoint.sections.keys() {
+            if !sections.iter().any(|section| &section.name == name) {
+                return Err(CheckpointError::UnknownSection(name.clone()));
+            }
+        }
+
+        // Read everything before modifying the context so that a failed
+        // restore leaves it unchanged
+        let mut states = Vec::new();
+        for section in &sections {
+            let value = checkpoint
+                .sections
+                .get(&section.name)
+                .ok_or_else(|| CheckpointError::MissingSection(section.name.clone()))?;
+            let state = (section.parse)(value.clone())
+                .map_err(|e| CheckpointError::InvalidSection(section.name.clone(), e))?;
+            states.push((Rc::clone(&section.apply), state));
+        }
+        let mut plans = Vec::new();
+        for plan in &checkpoint.plans {
+            let plan_loader = self
+                .get_data_container::<CheckpointRegistryPlugin>()
+                .and_then(|data_container| data_container.plan_loaders.get(&plan.name))
+                .ok_or_else(|| CheckpointError::UnknownPlan(plan.name.clone()))?;
+            let (saved_plan, callback) = plan_loader(plan.data.clone())?;
+            plans.push((plan.time, plan.phase, saved_plan, callback));
+        }
+
+        self.reset_plans(checkpoint.time);
+        for (apply, state) in states {
+            apply(self, state);
+        }
+        for (time, phase, saved_plan, callback) in plans {
+            self.add_saved_plan(time, phase, saved_plan, callback)
+                .expect("Checkpoint plan time is invalid");
+        }
+        crate::partitions::rebuild_partitions(self);
+        Ok(())
+    }
+
+    fn save_checkpoint(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let checkpoint = self.create_checkpoint()?;
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(writer, &checkpoint)?;
+        Ok(())
+    }
+
+    fn load_checkpoint(&mut self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let reader = BufReader::new(File::open(path)?);
+        let checkpoint: Checkpoint = serde_json::from_reader(reader)?;
+        self.restore_checkpoint(&checkpoint)
+    }
+
+    fn fork(&self, setup: impl FnOnce(&mut Context)) -> Result<Context, CheckpointError> {
+        let checkpoint = self.create_checkpoint()?;
+        let mut context = Context::new();
+        setup(&mut context);
+        context.restore_checkpoint(&checkpoint)?;
+        Ok(context)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::checkpoint::{CheckpointContext, CheckpointError, CheckpointPlan};
+    use crate::context::Context;
+    use crate::data_containers::PersonContainer;
+    use crate::global_properties::GlobalPropertyContext;
+    use crate::groups::{GroupId, GroupType, GroupsContext};
+    use crate::partitions::{Partition, PartitionContext};
+    use crate::people::{PeopleContext, PersonId};
+    use crate::person_properties::{PersonPropertyContext, PersonPropertyPartitionBuilder};
+    use crate::random::RandomContext;
+    use rand::RngCore;
+    use serde::{Deserialize, Serialize};
+
+    crate::define_person_property!(InfectionCount, u32, 0);
+    crate::define_global_property!(TransmissionRate, f64);
+    crate::define_random_id!(TransmissionRng, crate::random::CheckpointRng);
+
+    #[derive(Eq, PartialEq, Hash)]
+    struct Household {}
+    impl GroupType for Household {}
+
+    struct InfectionPartition {}
+    impl Partition for InfectionPartition {
+        type LabelType = u32;
+    }
+
+    crate::context::define_plugin!(DrawLog, Vec<(f64, u64)>, Vec::new());
+
+    #[derive(Serialize, Deserialize)]
+    struct InfectPerson {
+        person_id: PersonId,
+    }
+
+    impl CheckpointPlan for InfectPerson {
+        fn execute(self, context: &mut Context) {
+            let count = context.get_person_property_value::<InfectionCount>(self.person_id);
+            context.set_person_property_value::<InfectionCount>(self.person_id, count + 1);
+            let draw = context.get_rng::<TransmissionRng>().next_u64();
+            let time = context.get_time();
+            context
+                .get_or_init_data_container::<DrawLog>()
+                .push((time, draw));
+        }
+    }
+
+    fn setup(context: &mut Context) {
+        context.register_checkpoint_person_property::<InfectionCount>();
+        context.register_checkpoint_global_property::<TransmissionRate>();
+        context.register_checkpoint_group_type::<Household>();
+        context.register_checkpoint_rng::<TransmissionRng>();
+        context.register_checkpoint_plan::<InfectPerson>();
+        context
+            .add_partition::<InfectionPartition>()
+            .set_label_function(|context, person_id| {
+                context.get_person_property_value::<InfectionCount>(person_id)
+            })
+            .add_person_property_sensitivity::<InfectionCount>()
+            .execute();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut context = Context::new();
+        setup(&mut context);
+        context.set_base_random_seed(8675309);
+        context.set_global_property_value::<TransmissionRate>(0.5);
+        for _ in 0..3 {
+            context.add_person().execute().unwrap();
+        }
+        let household = context.add_group::<Household>();
+        context.add_person_to_group(PersonId::new(0), household);
+        context.add_person_to_group(PersonId::new(2), household);
+        for (time, person_id) in [(1.0, 0), (2.0, 1), (3.0, 1), (4.0, 2)] {
+            context.add_checkpoint_plan(
+                time,
+                InfectPerson {
+                    person_id: PersonId::new(person_id),
+                },
+            );
+        }
+        context.execute_until(2.5);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        context.save_checkpoint(&path).unwrap();
+        context.execute();
+        let expected_log = context.get_data_container::<DrawLog>().unwrap().clone();
+
+        let mut restored_context = Context::new();
+        setup(&mut restored_context);
+        restored_context.load_checkpoint(&path).unwrap();
+        assert_eq!(restored_context.get_time(), 2.5);
+        assert_eq!(restored_context.pending_plan_count(), 2);
+        assert_eq!(
+            restored_context.get_maximum_person_id(),
+            Some(PersonId::new(2))
+        );
+        assert_eq!(
+            *restored_context
+                .get_global_property_value::<TransmissionRate>()
+                .unwrap(),
+            0.5
+        );
+        assert!(restored_context.get_maximum_group_id::<Household>() == Some(GroupId::new(0)));
+        let members = restored_context.get_group_members(household).unwrap();
+        assert_eq!(members.len(), 2);
+        assert!(members.contains(&PersonId::new(2)));
+        assert_eq!(
+            restored_context
+                .get_partition_cell::<InfectionPartition>(1)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        restored_context.execute();
+        // The log itself isn't saved, so only the draws after the checkpoint are recorded
+        assert_eq!(
+            *restored_context.get_data_container::<DrawLog>().unwrap(),
+            expected_log[2..]
+        );
+        for person_id in 0..3 {
+            let person_id = PersonId::new(person_id);
+            assert_eq!(
+                restored_context.get_person_property_value::<InfectionCount>(person_id),
+                context.get_person_property_value::<InfectionCount>(person_id)
+            );
+        }
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut context = Context::new();
+        setup(&mut context);
+        context.set_base_random_seed(8675309);
+        context.add_person().execute().unwrap();
+        for time in 1..=4 {
+            context.add_checkpoint_plan(
+                time as f64,
+                InfectPerson {
+                    person_id: PersonId::new(0),
+                },
+            );
+        }
+        context.execute_until(2.0);
+
+        let mut arms = Vec::new();
+        for transmission_rate in [0.0, 0.0, 0.5] {
+            let mut arm = context.fork(setup).unwrap();
+            arm.set_global_property_value::<TransmissionRate>(transmission_rate);
+            arm.execute();
+            arms.push(arm);
+        }
+        // Forks don't change the original context
+        assert_eq!(context.pending_plan_count(), 2);
+        context.execute();
+
+        let expected_log = &context.get_data_container::<DrawLog>().unwrap()[2..];
+        for arm in &arms {
+            assert_eq!(arm.get_data_container::<DrawLog>().unwrap(), expected_log);
+            assert_eq!(
+                arm.get_person_property_value::<InfectionCount>(PersonId::new(0)),
+                4
+            );
+        }
+        assert_eq!(
+            *arms[2]
+                .get_global_property_value::<TransmissionRate>()
+                .unwrap(),
+            0.5
+        );
+    }
+
+    crate::define_person_property!(Age, u8, 0);
+    crate::define_random_id!(ContactRng);
+
+    #[test]
+    fn test_fork_unregistered_state() {
+        let mut context = Context::new();
+        context.set_base_random_seed(8675309);
+        let person_id = context.add_person().execute().unwrap();
+        context.set_person_property_value::<Age>(person_id, 30);
+        context.set_global_property_value::<TransmissionRate>(0.5);
+        context.get_rng::<ContactRng>().next_u64();
+        match context.fork(|_| {}) {
+            Err(CheckpointError::UnregisteredState(names)) => assert_eq!(
+                names,
+                vec![
+                    std::any::type_name::<Age>(),
+                    std::any::type_name::<ContactRng>(),
+                    std::any::type_name::<TransmissionRate>(),
+                ]
+            ),
+            _ => panic!("Expected unregistered state"),
+        }
+
+        // Rngs which have been reseeded and not used since are recreated
+        // identically, so they don't need to be registered
+        context.register_checkpoint_person_property::<Age>();
+        context.register_checkpoint_global_property::<TransmissionRate>();
+        context.set_base_random_seed(8675309);
+        let arm = context
+            .fork(|context| {
+                context.register_checkpoint_person_property::<Age>();
+                context.register_checkpoint_global_property::<TransmissionRate>();
+            })
+            .unwrap();
+        assert_eq!(arm.get_person_property_value::<Age>(person_id), 30);
+    }
+
+    #[test]
+    fn test_unknown_section() {
+        let mut context = Context::new();
+        context.register_checkpoint_global_property::<TransmissionRate>();
+        context.set_global_property_value::<TransmissionRate>(0.5);
+        let checkpoint = context.create_checkpoint().unwrap();
+
+        let mut restored_context = Context::new();
+        restored_context.add_plan(1.0, |_| {});
+        assert!(matches!(
+            restored_context.restore_checkpoint(&checkpoint),
+            Err(CheckpointError::UnknownSection(_))
+        ));
+        // A failed restore leaves the context unchanged
+        assert_eq!(restored_context.pending_plan_count(), 1);
+    }
+
+    #[test]
+    fn test_pending_callbacks() {
+        let mut context = Context::new();
+        context.queue_callback(|_| {});
+        assert!(matches!(
+            context.create_checkpoint(),
+            Err(CheckpointError::PendingCallbacks)
+        ));
+    }
+
+    #[test]
+    fn test_unsaved_plans() {
+        let mut context = Context::new();
+        context.add_checkpoint_plan(
+            1.0,
+            InfectPerson {
+                person_id: PersonId::new(0),
+            },
+        );
+        let plan_id = context.add_plan(1.0, |_| {});
+        let periodic_plan_id = context.add_periodic_plan(1.0, 1.0, |_| {});
+        assert!(matches!(
+            context.create_checkpoint(),
+            Err(CheckpointError::UnsavedPlans(2))
+        ));
+        context.cancel_plan(plan_id);
+        context.cancel_plan(periodic_plan_id);
+        assert_eq!(context.create_checkpoint().unwrap().plans.len(), 1);
+    }
+
+    #[test]
+    fn test_unregistered_rng() {
+        let mut context = Context::new();
+        context.set_base_random_seed(42);
+        context.get_rng::<TransmissionRng>().next_u64();
+        match context.create_checkpoint() {
+            Err(CheckpointError::UnregisteredState(names)) => {
+                assert_eq!(names, vec![std::any::type_name::<TransmissionRng>()])
+            }
+            _ => panic!("Expected unregistered state"),
+        }
+        // Reseeding restores the rng to a state the base seed recreates
+        context.set_base_random_seed(42);
+        assert!(context.create_checkpoint().is_ok());
+    }
+}
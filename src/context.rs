@@ -1,21 +1,108 @@
 use std::{
-    any::{Any, TypeId},
+    any::{type_name, Any, TypeId},
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    rc::Rc,
 };
 
 use derivative::Derivative;
+use fxhash::FxBuildHasher;
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::SavedPlan;
 
 pub trait Component: Any {
     fn init(context: &mut Context);
+
+    // Declares the components and plugins that must be initialized before
+    // this component
+    fn register_dependencies(_dependencies: &mut ComponentDependencies) {}
+}
+
+struct ComponentDependency {
+    add: fn(&mut Context) -> Result<(), ComponentError>,
 }
 
+pub struct ComponentDependencies {
+    dependencies: Vec<ComponentDependency>,
+}
+
+impl ComponentDependencies {
+    fn new() -> ComponentDependencies {
+        ComponentDependencies {
+            dependencies: Vec::new(),
+        }
+    }
+
+    pub fn add_component<T: Component>(&mut self) {
+        self.dependencies.push(ComponentDependency {
+            add: |context| context.try_add_component::<T>(),
+        });
+    }
+
+    pub fn add_plugin<T: Plugin>(&mut self) {
+        self.dependencies.push(ComponentDependency {
+            add: |context| {
+                context.register_plugin::<T>();
+                Ok(())
+            },
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentError {
+    // The names of the components in the cycle, starting and ending with
+    // the same component
+    DependencyCycle(Vec<&'static str>),
+}
+
+impl fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComponentError::DependencyCycle(names) => {
+                write!(f, "Component dependency cycle: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ComponentError {}
+
 pub trait Plugin: Any {
     type DataContainer;
 
     fn get_data_container() -> Self::DataContainer;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginError {
+    // The name of the plugin that was used before being registered
+    NotRegistered(&'static str),
+    // The name of something a plugin was asked for but doesn't have,
+    // e.g. a partition that was never added
+    NotAdded(&'static str),
+    // The name of a plugin whose data is already borrowed
+    AlreadyBorrowed(&'static str),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::NotRegistered(name) => {
+                write!(f, "Plugin {} has not been registered", name)
+            }
+            PluginError::NotAdded(name) => write!(f, "{} has not been added", name),
+            PluginError::AlreadyBorrowed(name) => {
+                write!(f, "Plugin {} is already borrowed", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
 #[macro_export]
 macro_rules! define_plugin {
     ($plugin:ident, $data_container:ty, $default: expr) => {
@@ -32,15 +119,61 @@ macro_rules! define_plugin {
 }
 pub use define_plugin;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PlanId {
     pub id: u64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanError {
+    NaNTime,
+    InfiniteTime(f64),
+    PastTime { time: f64, current_time: f64 },
+    PlanNotPending(PlanId),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::NaNTime => write!(f, "Plan time is NaN"),
+            PlanError::InfiniteTime(time) => write!(f, "Plan time {} is not finite", time),
+            PlanError::PastTime { time, current_time } => write!(
+                f,
+                "Plan time {} is before the current time {}",
+                time, current_time
+            ),
+            PlanError::PlanNotPending(plan_id) => {
+                write!(f, "Plan {} is not pending", plan_id.id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+// Determines how plans scheduled for a time before the current time are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PastPlanPolicy {
+    Reject,
+    ClampToCurrentTime,
+}
+
+// Plans scheduled for the same time are run in order of phase and then
+// in the order they were scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PlanPhase {
+    First,
+    Normal,
+    Last,
+}
+
 #[derive(Derivative)]
 #[derivative(Eq, PartialEq, Debug)]
 pub struct TimedPlan {
     pub time: f64,
+    pub phase: PlanPhase,
     plan_id: u64,
+    periodic: bool,
     #[derivative(PartialEq = "ignore", Debug = "ignore")]
     pub callback: Box<dyn FnOnce(&mut Context)>,
 }
@@ -49,8 +182,11 @@ impl Ord for TimedPlan {
     fn cmp(&self, other: &Self) -> Ordering {
         let time_ordering = self.time.partial_cmp(&other.time).unwrap().reverse();
         if time_ordering == Ordering::Equal {
-            // Break time ties in order of plan id
-            self.plan_id.cmp(&other.plan_id).reverse()
+            // Break time ties by phase and then in order of plan id
+            self.phase
+                .cmp(&other.phase)
+                .then(self.plan_id.cmp(&other.plan_id))
+                .reverse()
         } else {
             time_ordering
         }
@@ -63,63 +199,270 @@ impl PartialOrd for TimedPlan {
     }
 }
 
+// Binary heap of plans that also tracks the position of each plan so that
+// plans can be removed from the queue as soon as they are cancelled
 #[derive(Debug)]
 struct PlanQueue {
-    queue: BinaryHeap<TimedPlan>,
-    invalid_set: HashSet<u64>,
+    heap: Vec<TimedPlan>,
+    positions: HashMap<u64, usize, FxBuildHasher>,
+    // Periodic plans that have not been cancelled or reached their end
+    // (including the one that is currently executing)
+    active_periodic_plans: HashSet<u64, FxBuildHasher>,
     plan_counter: u64,
+    non_periodic_count: usize,
+    labels: HashMap<u64, &'static str, FxBuildHasher>,
+    // Serialized form of plans that can be saved in a checkpoint
+    saved_plans: HashMap<u64, SavedPlan, FxBuildHasher>,
+    // Number of plans in the queue with each label
+    label_counts: HashMap<&'static str, usize>,
 }
 
 impl PlanQueue {
     pub fn new() -> PlanQueue {
         PlanQueue {
-            queue: BinaryHeap::new(),
-            invalid_set: HashSet::new(),
+            heap: Vec::new(),
+            positions: HashMap::with_hasher(FxBuildHasher::default()),
+            active_periodic_plans: HashSet::with_hasher(FxBuildHasher::default()),
             plan_counter: 0,
+            non_periodic_count: 0,
+            labels: HashMap::with_hasher(FxBuildHasher::default()),
+            label_counts: HashMap::new(),
+            saved_plans: HashMap::with_hasher(FxBuildHasher::default()),
         }
     }
 
-    pub fn add_plan(&mut self, time: f64, callback: impl FnOnce(&mut Context) + 'static) -> PlanId {
+    pub fn add_plan(
+        &mut self,
+        time: f64,
+        phase: PlanPhase,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> PlanId {
         // Add plan to queue and increment counter
+        let plan_id = self.get_new_plan_id();
+        self.add_plan_with_id(plan_id, time, phase, false, callback);
+        PlanId { id: plan_id }
+    }
+
+    fn get_new_plan_id(&mut self) -> u64 {
         let plan_id = self.plan_counter;
-        self.queue.push(TimedPlan {
+        self.plan_counter += 1;
+        plan_id
+    }
+
+    fn add_plan_with_id(
+        &mut self,
+        plan_id: u64,
+        time: f64,
+        phase: PlanPhase,
+        periodic: bool,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) {
+        if !periodic {
+            self.non_periodic_count += 1;
+        }
+        self.heap.push(TimedPlan {
             time,
+            phase,
             plan_id,
+            periodic,
             callback: Box::new(callback),
         });
-        self.plan_counter += 1;
-        PlanId { id: plan_id }
+        let index = self.heap.len() - 1;
+        self.positions.insert(plan_id, index);
+        self.sift_up(index);
+        // Periodic plans keep their label when they are rescheduled
+        if let Some(label) = self.labels.get(&plan_id) {
+            *self.label_counts.entry(label).or_insert(0) += 1;
+        }
     }
 
-    pub fn cancel_plan(&mut self, id: PlanId) {
-        self.invalid_set.insert(id.id);
+    // Returns whether there was a pending plan to cancel
+    pub fn cancel_plan(&mut self, id: PlanId) -> bool {
+        let was_active_periodic = self.active_periodic_plans.remove(&id.id);
+        let was_queued = self.remove(id.id).is_some();
+        self.release_label(id.id);
+        was_active_periodic || was_queued
+    }
+
+    pub fn is_plan_pending(&self, id: PlanId) -> bool {
+        self.positions.contains_key(&id.id) || self.active_periodic_plans.contains(&id.id)
+    }
+
+    // Returns whether there was a queued plan to reschedule
+    pub fn reschedule_plan(&mut self, id: PlanId, time: f64) -> bool {
+        match self.positions.get(&id.id) {
+            None => false,
+            Some(&index) => {
+                self.heap[index].time = time;
+                self.sift_down(index);
+                self.sift_up(index);
+                true
+            }
+        }
+    }
+
+    pub fn pending_plan_count(&self) -> usize {
+        self.heap.len()
+    }
+
+    // Returns whether there was a pending plan to label
+    pub fn set_plan_label(&mut self, id: PlanId, label: &'static str) -> bool {
+        if !self.is_plan_pending(id) {
+            return false;
+        }
+        let is_queued = self.positions.contains_key(&id.id);
+        if is_queued {
+            if let Some(old_label) = self.labels.get(&id.id) {
+                self.decrement_label_count(old_label);
+            }
+            *self.label_counts.entry(label).or_insert(0) += 1;
+        }
+        self.labels.insert(id.id, label);
+        true
+    }
+
+    pub fn get_plan_label(&self, id: PlanId) -> Option<&'static str> {
+        self.labels.get(&id.id).copied()
+    }
+
+    pub fn pending_plan_count_with_label(&self, label: &'static str) -> usize {
+        self.label_counts.get(label).copied().unwrap_or(0)
+    }
+
+    pub fn pending_plan_counts_by_label(&self) -> &HashMap<&'static str, usize> {
+        &self.label_counts
+    }
+
+    fn decrement_label_count(&mut self, label: &'static str) {
+        let count = self.label_counts.get_mut(label).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.label_counts.remove(label);
+        }
+    }
+
+    // Forget the label of a plan that will not run again
+    fn release_label(&mut self, plan_id: u64) {
+        if !self.positions.contains_key(&plan_id) && !self.active_periodic_plans.contains(&plan_id)
+        {
+            self.labels.remove(&plan_id);
+        }
+    }
+
+    fn add_active_periodic_plan(&mut self, plan_id: u64) {
+        self.active_periodic_plans.insert(plan_id);
+    }
+
+    fn is_active_periodic_plan(&self, plan_id: u64) -> bool {
+        self.active_periodic_plans.contains(&plan_id)
+    }
+
+    fn finish_periodic_plan(&mut self, plan_id: u64) {
+        self.active_periodic_plans.remove(&plan_id);
+        self.release_label(plan_id);
+    }
+
+    pub fn has_non_periodic_plans(&self) -> bool {
+        self.non_periodic_count > 0
+    }
+
+    pub fn peek_next_timed_plan(&self) -> Option<&TimedPlan> {
+        self.heap.first()
     }
 
     pub fn get_next_timed_plan(&mut self) -> Option<TimedPlan> {
+        let plan_id = self.heap.first()?.plan_id;
+        self.remove(plan_id)
+    }
+
+    fn remove(&mut self, plan_id: u64) -> Option<TimedPlan> {
+        let index = self.positions.remove(&plan_id)?;
+        let timed_plan = self.heap.swap_remove(index);
+        if index < self.heap.len() {
+            // Restore the heap property for the plan moved into the gap
+            self.positions.insert(self.heap[index].plan_id, index);
+            self.sift_down(index);
+            self.sift_up(index);
+        }
+        if !timed_plan.periodic {
+            self.non_periodic_count -= 1;
+        }
+        if let Some(label) = self.labels.get(&plan_id) {
+            self.decrement_label_count(label);
+        }
+        self.release_label(plan_id);
+        self.saved_plans.remove(&plan_id);
+        Some(timed_plan)
+    }
+
+    // Removes all plans while continuing to issue new plan ids
+    fn clear(&mut self) {
+        *self = PlanQueue {
+            plan_counter: self.plan_counter,
+            ..PlanQueue::new()
+        };
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.positions.insert(self.heap[i].plan_id, i);
+        self.positions.insert(self.heap[j].plan_id, j);
+    }
+
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if self.heap[index] <= self.heap[parent] {
+                break;
+            }
+            self.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut index: usize) {
         loop {
-            let next_timed_plan = self.queue.pop();
-            match next_timed_plan {
-                Some(timed_plan) => {
-                    if self.invalid_set.contains(&timed_plan.plan_id) {
-                        self.invalid_set.remove(&timed_plan.plan_id);
-                    } else {
-                        return Some(timed_plan);
-                    }
-                }
-                None => {
-                    return None;
-                }
+            let left = 2 * index + 1;
+            let right = left + 1;
+            let mut largest = index;
+            if left < self.heap.len() && self.heap[left] > self.heap[largest] {
+                largest = left;
+            }
+            if right < self.heap.len() && self.heap[right] > self.heap[largest] {
+                largest = right;
+            }
+            if largest == index {
+                break;
             }
+            self.swap(index, largest);
+            index = largest;
         }
     }
 }
 
+struct PeriodicPlan {
+    plan_id: u64,
+    start: f64,
+    period: f64,
+    phase: PlanPhase,
+    end: Option<f64>,
+    callback: Rc<dyn Fn(&mut Context)>,
+}
+
 type Callback = dyn FnOnce(&mut Context);
 pub struct Context {
     plan_queue: PlanQueue,
     callback_queue: VecDeque<Box<Callback>>,
     plugin_data: HashMap<TypeId, Box<dyn Any>>,
     time: f64,
+    past_plan_policy: PastPlanPolicy,
+    shutdown_requested: bool,
+    components: HashSet<TypeId>,
+    // Components that are currently being initialized, in order
+    initializing_components: Vec<(TypeId, &'static str)>,
+    started: bool,
+    before_start_callbacks: Vec<Box<Callback>>,
+    after_end_callbacks: Vec<Box<Callback>>,
 }
 
 impl Context {
@@ -129,55 +472,301 @@ impl Context {
             callback_queue: VecDeque::new(),
             plugin_data: HashMap::new(),
             time: 0.0,
+            past_plan_policy: PastPlanPolicy::Reject,
+            shutdown_requested: false,
+            components: HashSet::new(),
+            initializing_components: Vec::new(),
+            started: false,
+            before_start_callbacks: Vec::new(),
+            after_end_callbacks: Vec::new(),
+        }
+    }
+
+    pub fn set_past_plan_policy(&mut self, policy: PastPlanPolicy) {
+        self.past_plan_policy = policy;
+    }
+
+    fn validate_plan_time(&self, time: f64) -> Result<f64, PlanError> {
+        if time.is_nan() {
+            return Err(PlanError::NaNTime);
+        }
+        if time.is_infinite() {
+            return Err(PlanError::InfiniteTime(time));
         }
+        if time < self.time {
+            return match self.past_plan_policy {
+                PastPlanPolicy::Reject => Err(PlanError::PastTime {
+                    time,
+                    current_time: self.time,
+                }),
+                PastPlanPolicy::ClampToCurrentTime => Ok(self.time),
+            };
+        }
+        Ok(time)
     }
 
     pub fn add_plan(&mut self, time: f64, callback: impl FnOnce(&mut Context) + 'static) -> PlanId {
-        // TODO: Handle invalid times (past, NAN, etc)
-        self.plan_queue.add_plan(time, callback)
+        self.add_plan_with_phase(time, PlanPhase::Normal, callback)
+    }
+
+    pub fn add_plan_with_phase(
+        &mut self,
+        time: f64,
+        phase: PlanPhase,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> PlanId {
+        match self.try_add_plan_with_phase(time, phase, callback) {
+            Ok(plan_id) => plan_id,
+            Err(e) => panic!("Invalid plan time: {}", e),
+        }
+    }
+
+    pub fn try_add_plan(
+        &mut self,
+        time: f64,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> Result<PlanId, PlanError> {
+        self.try_add_plan_with_phase(time, PlanPhase::Normal, callback)
+    }
+
+    pub fn try_add_plan_with_phase(
+        &mut self,
+        time: f64,
+        phase: PlanPhase,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> Result<PlanId, PlanError> {
+        let time = self.validate_plan_time(time)?;
+        Ok(self.plan_queue.add_plan(time, phase, callback))
+    }
+
+    pub(crate) fn add_saved_plan(
+        &mut self,
+        time: f64,
+        phase: PlanPhase,
+        saved_plan: SavedPlan,
+        callback: impl FnOnce(&mut Context) + 'static,
+    ) -> Result<PlanId, PlanError> {
+        let plan_id = self.try_add_plan_with_phase(time, phase, callback)?;
+        self.plan_queue.saved_plans.insert(plan_id.id, saved_plan);
+        Ok(plan_id)
+    }
+
+    // Returns the time, phase, and serialized form of each queued plan that
+    // can be saved in a checkpoint, in execution order
+    pub(crate) fn get_saved_plans(&self) -> Vec<(f64, PlanPhase, &SavedPlan)> {
+        let mut saved_plans: Vec<(&TimedPlan, &SavedPlan)> = self
+            .plan_queue
+            .saved_plans
+            .iter()
+            .map(|(plan_id, saved_plan)| {
+                let index = self.plan_queue.positions[plan_id];
+                (&self.plan_queue.heap[index], saved_plan)
+            })
+            .collect();
+        saved_plans.sort_by(|a, b| b.0.cmp(a.0));
+        saved_plans
+            .into_iter()
+            .map(|(timed_plan, saved_plan)| (timed_plan.time, timed_plan.phase, saved_plan))
+            .collect()
+    }
+
+    // Returns the number of queued plans, including periodic plans, which
+    // can't be saved in a checkpoint
+    pub(crate) fn unsaved_plan_count(&self) -> usize {
+        self.plan_queue.pending_plan_count() - self.plan_queue.saved_plans.len()
+    }
+
+    // Drops all pending plans and callbacks and moves the clock to the
+    // given time, which may be earlier than the current time
+    pub(crate) fn reset_plans(&mut self, time: f64) {
+        self.plan_queue.clear();
+        self.callback_queue.clear();
+        self.time = time;
+    }
+
+    pub(crate) fn has_queued_callbacks(&self) -> bool {
+        !self.callback_queue.is_empty()
+    }
+
+    // Schedules a plan that runs every `period` starting at `start`. The
+    // returned PlanId can be used to cancel all future executions.
+    pub fn add_periodic_plan(
+        &mut self,
+        start: f64,
+        period: f64,
+        callback: impl Fn(&mut Context) + 'static,
+    ) -> PlanId {
+        self.add_periodic_plan_with_options(start, period, PlanPhase::Normal, None, callback)
+    }
+
+    // Schedules a periodic plan in the given phase that stops once the
+    // next execution would be after `end` (if specified).
+    pub fn add_periodic_plan_with_options(
+        &mut self,
+        start: f64,
+        period: f64,
+        phase: PlanPhase,
+        end: Option<f64>,
+        callback: impl Fn(&mut Context) + 'static,
+    ) -> PlanId {
+        if !(period.is_finite() && period > 0.0) {
+            panic!("Invalid plan period: {}", period);
+        }
+        let start = match self.validate_plan_time(start) {
+            Ok(start) => start,
+            Err(e) => panic!("Invalid plan time: {}", e),
+        };
+        let plan_id = self.plan_queue.get_new_plan_id();
+        let periodic_plan = PeriodicPlan {
+            plan_id,
+            start,
+            period,
+            phase,
+            end,
+            callback: Rc::new(callback),
+        };
+        self.plan_queue.add_active_periodic_plan(plan_id);
+        self.schedule_periodic_plan(periodic_plan, 0);
+        PlanId { id: plan_id }
+    }
+
+    // Queues the given execution of a periodic plan, skipping any that
+    // would be before the current time, or finishes the plan if the
+    // execution would be after its end
+    fn schedule_periodic_plan(&mut self, mut periodic_plan: PeriodicPlan, iteration: u64) {
+        let get_time =
+            |iteration: u64| periodic_plan.start + (iteration as f64) * periodic_plan.period;
+        let mut iteration = iteration;
+        let mut time = get_time(iteration);
+        while time < self.time {
+            iteration += 1;
+            time = get_time(iteration);
+        }
+        let (plan_id, phase) = (periodic_plan.plan_id, periodic_plan.phase);
+        if periodic_plan.end.is_some_and(|end| time > end) {
+            self.plan_queue.finish_periodic_plan(plan_id);
+            return;
+        }
+        self.plan_queue
+            .add_plan_with_id(plan_id, time, phase, true, move |context| {
+                (periodic_plan.callback)(context);
+                // Don't reschedule if the plan was cancelled by its own callback
+                if !context.plan_queue.is_active_periodic_plan(plan_id) {
+                    return;
+                }
+                // A rescheduled execution moves the rest of the schedule
+                // along with it
+                if context.time == time {
+                    context.schedule_periodic_plan(periodic_plan, iteration + 1);
+                } else {
+                    periodic_plan.start = context.time;
+                    context.schedule_periodic_plan(periodic_plan, 1);
+                }
+            });
+    }
+
+    // Returns whether there was a pending plan to cancel
+    pub fn cancel_plan(&mut self, id: PlanId) -> bool {
+        self.plan_queue.cancel_plan(id)
+    }
+
+    // Moves a pending plan to a new time, keeping its callback and id. For
+    // a periodic plan the next execution is moved and later executions
+    // follow it at the same period.
+    pub fn reschedule_plan(&mut self, id: PlanId, time: f64) -> Result<(), PlanError> {
+        if !self.plan_queue.positions.contains_key(&id.id) {
+            return Err(PlanError::PlanNotPending(id));
+        }
+        let time = self.validate_plan_time(time)?;
+        self.plan_queue.reschedule_plan(id, time);
+        Ok(())
+    }
+
+    pub fn is_plan_pending(&self, id: PlanId) -> bool {
+        self.plan_queue.is_plan_pending(id)
+    }
+
+    pub fn pending_plan_count(&self) -> usize {
+        self.plan_queue.pending_plan_count()
     }
 
-    pub fn cancel_plan(&mut self, id: PlanId) {
-        self.plan_queue.cancel_plan(id);
+    pub fn next_plan_time(&self) -> Option<f64> {
+        self.plan_queue
+            .peek_next_timed_plan()
+            .map(|timed_plan| timed_plan.time)
+    }
+
+    // Attaches a label to a pending plan so that plans can be counted by
+    // label. Returns whether there was a pending plan to label.
+    pub fn set_plan_label(&mut self, id: PlanId, label: &'static str) -> bool {
+        self.plan_queue.set_plan_label(id, label)
+    }
+
+    pub fn get_plan_label(&self, id: PlanId) -> Option<&'static str> {
+        self.plan_queue.get_plan_label(id)
+    }
+
+    pub fn pending_plan_count_with_label(&self, label: &'static str) -> usize {
+        self.plan_queue.pending_plan_count_with_label(label)
+    }
+
+    pub fn pending_plan_counts_by_label(&self) -> &HashMap<&'static str, usize> {
+        self.plan_queue.pending_plan_counts_by_label()
     }
 
     pub fn queue_callback(&mut self, callback: impl FnOnce(&mut Context) + 'static) {
         self.callback_queue.push_back(Box::new(callback));
     }
 
-    fn add_plugin<T: Plugin>(&mut self) {
+    // Adds the data container for a plugin if it hasn't been added yet
+    pub fn register_plugin<T: Plugin>(&mut self) {
         self.plugin_data
-            .insert(TypeId::of::<T>(), Box::new(T::get_data_container()));
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::get_data_container()));
+    }
+
+    pub fn is_plugin_registered<T: Plugin>(&self) -> bool {
+        self.plugin_data.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get_or_init_data_container<T: Plugin>(&mut self) -> &mut T::DataContainer {
+        self.register_plugin::<T>();
+        self.try_get_data_container_mut::<T>().unwrap()
     }
 
+    #[deprecated(
+        note = "use get_or_init_data_container, or try_get_data_container_mut to avoid adding the plugin"
+    )]
     pub fn get_data_container_mut<T: Plugin>(&mut self) -> &mut T::DataContainer {
-        let type_id = &TypeId::of::<T>();
-        if !self.plugin_data.contains_key(type_id) {
-            self.add_plugin::<T>();
-        }
+        self.get_or_init_data_container::<T>()
+    }
+
+    pub fn get_data_container<T: Plugin>(&self) -> Option<&T::DataContainer> {
+        self.try_get_data_container::<T>().ok()
+    }
+
+    pub fn try_get_data_container<T: Plugin>(&self) -> Result<&T::DataContainer, PluginError> {
         let data_container = self
             .plugin_data
-            .get_mut(type_id)
-            .unwrap()
-            .downcast_mut::<T::DataContainer>();
+            .get(&TypeId::of::<T>())
+            .ok_or(PluginError::NotRegistered(type_name::<T>()))?
+            .downcast_ref::<T::DataContainer>();
         match data_container {
-            Some(x) => x,
+            Some(x) => Ok(x),
             None => panic!("Plugin data container of incorrect type"),
         }
     }
 
-    pub fn get_data_container<T: Plugin>(&self) -> Option<&T::DataContainer> {
-        let type_id = &TypeId::of::<T>();
-        if !self.plugin_data.contains_key(type_id) {
-            return None;
-        }
+    pub fn try_get_data_container_mut<T: Plugin>(
+        &mut self,
+    ) -> Result<&mut T::DataContainer, PluginError> {
         let data_container = self
             .plugin_data
-            .get(type_id)
-            .unwrap()
-            .downcast_ref::<T::DataContainer>();
+            .get_mut(&TypeId::of::<T>())
+            .ok_or(PluginError::NotRegistered(type_name::<T>()))?
+            .downcast_mut::<T::DataContainer>();
         match data_container {
-            Some(x) => Some(x),
+            Some(x) => Ok(x),
             None => panic!("Plugin data container of incorrect type"),
         }
     }
@@ -186,12 +775,106 @@ impl Context {
         self.time
     }
 
+    // Initializes a component and its dependencies. Components that have
+    // already been added are not initialized again.
     pub fn add_component<T: Component>(&mut self) {
+        if let Err(e) = self.try_add_component::<T>() {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_add_component<T: Component>(&mut self) -> Result<(), ComponentError> {
+        let type_id = TypeId::of::<T>();
+        if self.components.contains(&type_id) {
+            return Ok(());
+        }
+        if let Some(index) = self
+            .initializing_components
+            .iter()
+            .position(|(component_type_id, _)| *component_type_id == type_id)
+        {
+            let mut cycle: Vec<&'static str> = self.initializing_components[index..]
+                .iter()
+                .map(|(_, name)| *name)
+                .collect();
+            cycle.push(type_name::<T>());
+            return Err(ComponentError::DependencyCycle(cycle));
+        }
+
+        self.initializing_components
+            .push((type_id, type_name::<T>()));
+        let mut dependencies = ComponentDependencies::new();
+        T::register_dependencies(&mut dependencies);
+        for dependency in dependencies.dependencies {
+            if let Err(e) = (dependency.add)(self) {
+                self.initializing_components.pop();
+                return Err(e);
+            }
+        }
         T::init(self);
+        self.initializing_components.pop();
+        self.components.insert(type_id);
+        Ok(())
+    }
+
+    pub fn has_component<T: Component>(&self) -> bool {
+        self.components.contains(&TypeId::of::<T>())
+    }
+
+    // Stops execution once the callbacks currently queued have run.
+    // Pending plans are left in the queue, so execution can be resumed.
+    pub fn shutdown(&mut self) {
+        self.shutdown_requested = true;
+    }
+
+    // Adds a callback which runs when execution first begins, before any
+    // plans. Callbacks run in the order they were added.
+    pub fn add_before_start_callback(&mut self, callback: impl FnOnce(&mut Context) + 'static) {
+        if self.started {
+            panic!("Cannot add a before start callback after the simulation has started");
+        }
+        self.before_start_callbacks.push(Box::new(callback));
+    }
+
+    // Adds a callback which runs once the simulation ends, either because
+    // no plans remain or because of a shutdown. This applies to both
+    // execute() and execute_until(), but execute_until() stopping at its
+    // time with plans still queued does not end the simulation.
+    pub fn add_after_end_callback(&mut self, callback: impl FnOnce(&mut Context) + 'static) {
+        self.after_end_callbacks.push(Box::new(callback));
+    }
+
+    // Returns whether execution has begun, i.e., the before start
+    // callbacks have run
+    pub fn has_started(&self) -> bool {
+        self.started
     }
 
     pub fn execute(&mut self) {
-        // Execute callbacks if there are any in the queue
+        self.run(None);
+        self.end();
+    }
+
+    // Executes all plans scheduled for times up to and including the
+    // given time, then advances the current time to it. Later plans are
+    // left in the queue. Unlike execute(), periodic plans keep running
+    // until the given time even if no other plans remain. Panics if the
+    // time is NaN or infinite.
+    pub fn execute_until(&mut self, time: f64) {
+        // The clock can't be moved to a time plans couldn't be added after
+        if !time.is_finite() {
+            panic!("Cannot execute until a non-finite time {}", time);
+        }
+        let ended = self.run(Some(time));
+        if !self.shutdown_requested && time > self.time {
+            self.time = time;
+        }
+        if ended {
+            self.end();
+        }
+    }
+
+    fn execute_callbacks(&mut self) {
         loop {
             let callback = self.callback_queue.pop_front();
             match callback {
@@ -199,24 +882,64 @@ impl Context {
                 None => break,
             }
         }
+    }
+
+    fn start(&mut self) {
+        // Before start callbacks may add more before start callbacks
+        while !self.before_start_callbacks.is_empty() {
+            let callbacks = std::mem::take(&mut self.before_start_callbacks);
+            for callback in callbacks {
+                callback(self);
+            }
+        }
+        self.started = true;
+    }
+
+    fn end(&mut self) {
+        // After end callbacks may add more after end callbacks
+        while !self.after_end_callbacks.is_empty() {
+            let callbacks = std::mem::take(&mut self.after_end_callbacks);
+            for callback in callbacks {
+                callback(self);
+                self.execute_callbacks();
+            }
+        }
+    }
+
+    // Returns whether the simulation ended, i.e., a shutdown was requested
+    // or no plans remain
+    fn run(&mut self, horizon: Option<f64>) -> bool {
+        if !self.started {
+            self.start();
+        }
+        self.shutdown_requested = false;
+        // Execute callbacks if there are any in the queue
+        self.execute_callbacks();
         // Start plan loop
-        loop {
-            let timed_plan = self.plan_queue.get_next_timed_plan();
-            match timed_plan {
-                Some(timed_plan) => {
-                    self.time = timed_plan.time;
-                    (timed_plan.callback)(self);
-                    loop {
-                        let callback = self.callback_queue.pop_front();
-                        match callback {
-                            Some(callback) => callback(self),
-                            None => break,
+        while !self.shutdown_requested {
+            let has_non_periodic_plans = self.plan_queue.has_non_periodic_plans();
+            match self.plan_queue.peek_next_timed_plan() {
+                None => return true,
+                Some(timed_plan) => match horizon {
+                    Some(horizon) => {
+                        if timed_plan.time > horizon {
+                            break;
                         }
                     }
-                }
-                None => break,
+                    None => {
+                        // Stop once only periodic plans remain
+                        if timed_plan.periodic && !has_non_periodic_plans {
+                            break;
+                        }
+                    }
+                },
             }
+            let timed_plan = self.plan_queue.get_next_timed_plan().unwrap();
+            self.time = timed_plan.time;
+            (timed_plan.callback)(self);
+            self.execute_callbacks();
         }
+        self.shutdown_requested
     }
 }
 
@@ -227,8 +950,10 @@ impl Default for Context {
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use super::*;
+    use std::cell::RefCell;
 
     define_plugin!(ComponentA, u32, 0);
 
@@ -260,4 +985,493 @@ mod tests {
         assert_eq!(context.get_time(), 2.0);
         assert_eq!(*context.get_data_container_mut::<ComponentA>(), 2);
     }
+
+    #[test]
+    fn test_invalid_plan_times() {
+        let mut context = Context::new();
+        context.add_plan(1.0, |_context| {});
+        context.execute();
+        assert_eq!(
+            context.try_add_plan(f64::NAN, |_context| {}).err(),
+            Some(PlanError::NaNTime)
+        );
+        assert_eq!(
+            context.try_add_plan(f64::INFINITY, |_context| {}).err(),
+            Some(PlanError::InfiniteTime(f64::INFINITY))
+        );
+        assert_eq!(
+            context.try_add_plan(0.5, |_context| {}).err(),
+            Some(PlanError::PastTime {
+                time: 0.5,
+                current_time: 1.0
+            })
+        );
+        assert!(context.try_add_plan(1.0, |_context| {}).is_ok());
+        context.execute();
+        assert_eq!(context.get_time(), 1.0);
+    }
+
+    #[test]
+    fn test_clamp_past_plan_times() {
+        let mut context = Context::new();
+        context.add_plan(2.0, |_context| {});
+        context.execute();
+        context.set_past_plan_policy(PastPlanPolicy::ClampToCurrentTime);
+        context.add_plan(1.0, ComponentA::increment_counter);
+        context.execute();
+        assert_eq!(context.get_time(), 2.0);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 1);
+        // NaN times are rejected regardless of policy
+        assert_eq!(
+            context.try_add_plan(f64::NAN, |_context| {}).err(),
+            Some(PlanError::NaNTime)
+        );
+    }
+
+    define_plugin!(ExecutionOrder, Vec<&'static str>, Vec::new());
+
+    fn record_execution(label: &'static str) -> impl Fn(&mut Context) {
+        move |context| {
+            context
+                .get_data_container_mut::<ExecutionOrder>()
+                .push(label)
+        }
+    }
+
+    #[test]
+    fn test_plan_phases() {
+        let mut context = Context::new();
+        context.add_plan_with_phase(1.0, PlanPhase::Last, record_execution("last"));
+        context.add_plan(1.0, record_execution("normal_1"));
+        context.add_plan_with_phase(1.0, PlanPhase::First, record_execution("first"));
+        context.add_plan(1.0, record_execution("normal_2"));
+        context.add_plan_with_phase(0.5, PlanPhase::Last, record_execution("earlier"));
+        context.execute();
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["earlier", "first", "normal_1", "normal_2", "last"]
+        );
+    }
+
+    #[test]
+    fn test_execute_until() {
+        let mut context = Context::new();
+        context.add_plan(1.0, ComponentA::increment_counter);
+        context.add_plan(2.0, ComponentA::increment_counter);
+        context.add_plan(3.0, ComponentA::increment_counter);
+        context.execute_until(2.0);
+        assert_eq!(context.get_time(), 2.0);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 2);
+        context.execute_until(2.5);
+        assert_eq!(context.get_time(), 2.5);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 2);
+        context.execute();
+        assert_eq!(context.get_time(), 3.0);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 3);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let mut context = Context::new();
+        context.add_plan(1.0, |context| {
+            context.shutdown();
+            context.queue_callback(ComponentA::increment_counter);
+        });
+        context.add_plan(1.0, ComponentA::increment_counter);
+        context.add_plan(2.0, ComponentA::increment_counter);
+        context.execute();
+        // Queued callbacks still run but remaining plans do not
+        assert_eq!(context.get_time(), 1.0);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 1);
+        // Execution can be resumed
+        context.execute();
+        assert_eq!(context.get_time(), 2.0);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 3);
+    }
+
+    #[test]
+    fn test_lifecycle_callbacks() {
+        let mut context = Context::new();
+        context.add_after_end_callback(record_execution("end"));
+        context.add_before_start_callback(|context| {
+            assert!(!context.has_started());
+            context.add_before_start_callback(record_execution("nested_start"));
+            record_execution("start")(context);
+        });
+        context.add_plan(1.0, record_execution("plan"));
+        assert!(!context.has_started());
+        context.execute_until(0.5);
+        assert!(context.has_started());
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["start", "nested_start"]
+        );
+        context.execute();
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["start", "nested_start", "plan", "end"]
+        );
+    }
+
+    #[test]
+    fn test_after_end_callbacks_with_execute_until() {
+        let mut context = Context::new();
+        context.add_after_end_callback(record_execution("end"));
+        context.add_plan(1.0, record_execution("plan"));
+        context.add_plan(2.0, |context| {
+            record_execution("shutdown")(context);
+            context.shutdown();
+        });
+        context.add_plan(3.0, record_execution("plan"));
+        // Stopping with plans still queued does not end the simulation
+        context.execute_until(1.5);
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["plan"]
+        );
+        context.execute_until(2.5);
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["plan", "shutdown", "end"]
+        );
+        // Running out of plans also ends the simulation
+        context.add_after_end_callback(record_execution("end"));
+        context.execute_until(5.0);
+        assert_eq!(context.get_time(), 5.0);
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["plan", "shutdown", "end", "plan", "end"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "after the simulation has started")]
+    fn test_before_start_callback_after_start() {
+        let mut context = Context::new();
+        context.execute();
+        context.add_before_start_callback(|_context| {});
+    }
+
+    #[test]
+    fn test_periodic_plan() {
+        let mut context = Context::new();
+        context.add_periodic_plan(1.0, 1.0, ComponentA::increment_counter);
+        context.add_plan(3.5, |_context| {});
+        // Periodic plans stop once no other plans remain
+        context.execute();
+        assert_eq!(context.get_time(), 3.5);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 3);
+        // But keep running until the horizon in execute_until
+        context.execute_until(6.0);
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 6);
+    }
+
+    #[test]
+    fn test_periodic_plan_end_and_cancel() {
+        let mut context = Context::new();
+        context.add_periodic_plan_with_options(
+            0.0,
+            0.5,
+            PlanPhase::Last,
+            Some(1.0),
+            record_execution("periodic"),
+        );
+        context.add_plan(1.0, record_execution("normal"));
+        let plan_to_cancel = context.add_periodic_plan(0.0, 1.0, |_context| {});
+        context.add_plan(0.5, move |context| {
+            context.cancel_plan(plan_to_cancel);
+        });
+        context.add_plan(10.0, |_context| {});
+        context.execute();
+        assert_eq!(context.get_time(), 10.0);
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["periodic", "periodic", "normal", "periodic"]
+        );
+    }
+
+    #[test]
+    fn test_cancel_plan() {
+        let mut context = Context::new();
+        let plan_one = context.add_plan(1.0, ComponentA::increment_counter);
+        let plan_two = context.add_plan(2.0, ComponentA::increment_counter);
+        let periodic_plan = context.add_periodic_plan(0.5, 1.0, |_context| {});
+        assert!(context.cancel_plan(plan_one));
+        assert!(!context.cancel_plan(plan_one));
+        // Cancelled plans are removed from the queue immediately
+        assert_eq!(context.plan_queue.heap.len(), 2);
+        context.execute();
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 1);
+        // Plans that have already run can't be cancelled
+        assert!(!context.cancel_plan(plan_two));
+        assert!(context.cancel_plan(periodic_plan));
+        assert!(context.plan_queue.heap.is_empty());
+        assert!(context.plan_queue.active_periodic_plans.is_empty());
+        assert!(!context.cancel_plan(PlanId { id: 100 }));
+    }
+
+    #[test]
+    fn test_periodic_plan_cancels_itself() {
+        let mut context = Context::new();
+        let plan_ids = Rc::new(RefCell::new(HashMap::new()));
+        let plan_ids_clone = Rc::clone(&plan_ids);
+        let plan_id = context.add_periodic_plan(1.0, 1.0, move |context| {
+            ComponentA::increment_counter(context);
+            if *context.get_data_container_mut::<ComponentA>() == 2 {
+                let plan_id = plan_ids_clone.borrow()["periodic"];
+                assert!(context.cancel_plan(plan_id));
+            }
+        });
+        plan_ids.borrow_mut().insert("periodic", plan_id);
+        context.add_plan(5.0, |_context| {});
+        context.execute();
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 2);
+    }
+
+    define_plugin!(ExecutionTimes, Vec<f64>, Vec::new());
+
+    #[test]
+    fn test_plan_order_with_cancellation() {
+        let mut context = Context::new();
+        let mut plan_ids = Vec::new();
+        for i in 0..100 {
+            let time = ((i * 37) % 101) as f64;
+            plan_ids.push(context.add_plan(time, |context| {
+                let time = context.get_time();
+                context
+                    .get_data_container_mut::<ExecutionTimes>()
+                    .push(time);
+            }));
+        }
+        for plan_id in plan_ids.iter().step_by(3) {
+            assert!(context.cancel_plan(*plan_id));
+        }
+        context.execute();
+        let execution_times = context.get_data_container_mut::<ExecutionTimes>();
+        assert_eq!(execution_times.len(), 66);
+        assert!(execution_times.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_plan_introspection() {
+        let mut context = Context::new();
+        assert_eq!(context.next_plan_time(), None);
+        assert_eq!(context.pending_plan_count(), 0);
+        let plan_one = context.add_plan(2.0, |_context| {});
+        let plan_two = context.add_plan(1.0, |context| {
+            let follow_up = context.add_plan(3.0, |_context| {});
+            context.set_plan_label(follow_up, "follow_up");
+        });
+        let periodic_plan = context.add_periodic_plan(0.5, 1.0, |_context| {});
+        assert!(context.set_plan_label(plan_one, "one"));
+        assert!(context.set_plan_label(periodic_plan, "report"));
+        assert_eq!(context.next_plan_time(), Some(0.5));
+        assert_eq!(context.pending_plan_count(), 3);
+        assert!(context.is_plan_pending(plan_one));
+        assert_eq!(context.pending_plan_count_with_label("one"), 1);
+        assert_eq!(context.pending_plan_count_with_label("report"), 1);
+        assert_eq!(context.get_plan_label(plan_two), None);
+
+        context.execute_until(1.0);
+        assert!(!context.is_plan_pending(plan_two));
+        assert!(!context.set_plan_label(plan_two, "two"));
+        assert_eq!(context.next_plan_time(), Some(1.5));
+        assert_eq!(context.pending_plan_count_with_label("follow_up"), 1);
+        // Periodic plans keep their labels
+        assert_eq!(context.pending_plan_count_with_label("report"), 1);
+        assert_eq!(context.get_plan_label(periodic_plan), Some("report"));
+
+        // Relabeling moves the plan between counts
+        assert!(context.set_plan_label(plan_one, "two"));
+        assert_eq!(context.pending_plan_count_with_label("one"), 0);
+        assert_eq!(context.pending_plan_count_with_label("two"), 1);
+        assert_eq!(context.pending_plan_counts_by_label().len(), 3);
+
+        context.cancel_plan(periodic_plan);
+        context.execute();
+        assert!(!context.is_plan_pending(periodic_plan));
+        assert_eq!(context.get_plan_label(periodic_plan), None);
+        assert_eq!(context.pending_plan_count(), 0);
+        assert!(context.pending_plan_counts_by_label().is_empty());
+        assert!(context.plan_queue.labels.is_empty());
+    }
+
+    #[test]
+    fn test_reschedule_plan() {
+        let mut context = Context::new();
+        let plan_one = context.add_plan(1.0, record_execution("one"));
+        let plan_two = context.add_plan(2.0, record_execution("two"));
+        context.add_plan(3.0, record_execution("three"));
+        assert_eq!(context.reschedule_plan(plan_one, 2.5), Ok(()));
+        assert_eq!(context.reschedule_plan(plan_two, 0.5), Ok(()));
+        assert_eq!(context.next_plan_time(), Some(0.5));
+        assert_eq!(
+            context.reschedule_plan(plan_one, f64::NAN),
+            Err(PlanError::NaNTime)
+        );
+        context.execute_until(1.0);
+        assert_eq!(
+            context.reschedule_plan(plan_two, 4.0),
+            Err(PlanError::PlanNotPending(plan_two))
+        );
+        assert_eq!(
+            context.reschedule_plan(plan_one, 0.5),
+            Err(PlanError::PastTime {
+                time: 0.5,
+                current_time: 1.0
+            })
+        );
+        context.cancel_plan(plan_one);
+        assert_eq!(
+            context.reschedule_plan(plan_one, 4.0),
+            Err(PlanError::PlanNotPending(plan_one))
+        );
+        context.execute();
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["two", "three"]
+        );
+    }
+
+    #[test]
+    fn test_reschedule_periodic_plan() {
+        let mut context = Context::new();
+        let periodic_plan = context.add_periodic_plan(1.0, 1.0, |context| {
+            let time = context.get_time();
+            context
+                .get_data_container_mut::<ExecutionTimes>()
+                .push(time);
+        });
+        context.execute_until(2.0);
+        assert_eq!(context.reschedule_plan(periodic_plan, 4.5), Ok(()));
+        context.execute_until(7.0);
+        context.reschedule_plan(periodic_plan, 7.25).unwrap();
+        context.execute_until(9.0);
+        let execution_times = context.get_data_container_mut::<ExecutionTimes>();
+        assert_eq!(*execution_times, vec![1.0, 2.0, 4.5, 5.5, 6.5, 7.25, 8.25]);
+        assert!(execution_times.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(context.get_time(), 9.0);
+    }
+
+    struct ComponentB {}
+
+    impl Component for ComponentB {
+        fn init(context: &mut Context) {
+            assert!(context.has_component::<ComponentA>());
+            context.get_data_container_mut::<ExecutionOrder>().push("b");
+        }
+
+        fn register_dependencies(dependencies: &mut ComponentDependencies) {
+            dependencies.add_component::<ComponentA>();
+            dependencies.add_plugin::<ExecutionTimes>();
+        }
+    }
+
+    struct ComponentC {}
+
+    impl Component for ComponentC {
+        fn init(context: &mut Context) {
+            context.get_data_container_mut::<ExecutionOrder>().push("c");
+        }
+
+        fn register_dependencies(dependencies: &mut ComponentDependencies) {
+            dependencies.add_component::<ComponentB>();
+            dependencies.add_component::<ComponentA>();
+        }
+    }
+
+    #[test]
+    fn test_component_dependencies() {
+        let mut context = Context::new();
+        context.add_component::<ComponentC>();
+        context.add_component::<ComponentB>();
+        context.add_component::<ComponentA>();
+        assert!(context.get_data_container::<ExecutionTimes>().is_some());
+        assert_eq!(
+            *context.get_data_container_mut::<ExecutionOrder>(),
+            vec!["b", "c"]
+        );
+        // ComponentA is only initialized once, so only one plan is added
+        assert_eq!(context.pending_plan_count(), 1);
+    }
+
+    struct CycleA {}
+
+    impl Component for CycleA {
+        fn init(_context: &mut Context) {}
+
+        fn register_dependencies(dependencies: &mut ComponentDependencies) {
+            dependencies.add_component::<CycleB>();
+        }
+    }
+
+    struct CycleB {}
+
+    impl Component for CycleB {
+        fn init(_context: &mut Context) {}
+
+        fn register_dependencies(dependencies: &mut ComponentDependencies) {
+            dependencies.add_component::<CycleA>();
+        }
+    }
+
+    #[test]
+    fn test_component_dependency_cycle() {
+        let mut context = Context::new();
+        let result = context.try_add_component::<CycleA>();
+        assert_eq!(
+            result,
+            Err(ComponentError::DependencyCycle(vec![
+                type_name::<CycleA>(),
+                type_name::<CycleB>(),
+                type_name::<CycleA>()
+            ]))
+        );
+        assert!(!context.has_component::<CycleA>());
+        assert!(!context.has_component::<CycleB>());
+        assert!(context.initializing_components.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_registration() {
+        let mut context = Context::new();
+        assert!(!context.is_plugin_registered::<ComponentA>());
+        assert!(context.get_data_container::<ComponentA>().is_none());
+        assert_eq!(
+            context.try_get_data_container::<ComponentA>().err(),
+            Some(PluginError::NotRegistered(type_name::<ComponentA>()))
+        );
+        assert!(context.try_get_data_container_mut::<ComponentA>().is_err());
+
+        context.register_plugin::<ComponentA>();
+        assert!(context.is_plugin_registered::<ComponentA>());
+        assert_eq!(context.try_get_data_container::<ComponentA>(), Ok(&0));
+        *context.try_get_data_container_mut::<ComponentA>().unwrap() = 2;
+        // Registering again doesn't reset the data container
+        context.register_plugin::<ComponentA>();
+        assert_eq!(*context.get_data_container_mut::<ComponentA>(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid plan time")]
+    fn test_add_plan_past_time_panics() {
+        let mut context = Context::new();
+        context.add_plan(2.0, |_context| {});
+        context.execute();
+        context.add_plan(1.0, |_context| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot execute until a non-finite time inf")]
+    fn test_execute_until_infinite_time() {
+        let mut context = Context::new();
+        context.execute_until(f64::INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot execute until a non-finite time NaN")]
+    fn test_execute_until_nan_time() {
+        let mut context = Context::new();
+        context.execute_until(f64::NAN);
+    }
 }
@@ -1,16 +1,96 @@
 use crate::context::Context;
+use std::any::{type_name, Any, TypeId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 
 type CreatorFn<T> = dyn FnOnce(&mut Context) -> T;
 type CreationCallback<T> = dyn FnOnce(&mut Context, T);
 
-pub struct CreationBuilder<'a, T: Copy + Clone> {
+// Returned by execute when required initializers were not called. Nothing
+// is created in that case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreationError {
+    pub missing_initializers: Vec<&'static str>,
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Missing required initializers: {}",
+            self.missing_initializers.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for CreationError {}
+
+struct CreationDataContainer {
+    // Maps TypeId::of::<T>() for the created type to the initializers
+    // every builder of T must call, by type name
+    required_initializers: HashMap<TypeId, BTreeMap<&'static str, TypeId>>,
+}
+
+crate::context::define_plugin!(
+    CreationPlugin,
+    CreationDataContainer,
+    CreationDataContainer {
+        required_initializers: HashMap::new(),
+    }
+);
+
+// Checks that a builder of T which called the given initializers may
+// create T
+pub(crate) fn check_required_initializers<T: Any>(
+    context: &Context,
+    initialized: &HashSet<TypeId>,
+) -> Result<(), CreationError> {
+    let Some(required_initializers) = context
+        .get_data_container::<CreationPlugin>()
+        .and_then(|data_container| data_container.required_initializers.get(&TypeId::of::<T>()))
+    else {
+        return Ok(());
+    };
+    let missing_initializers: Vec<&'static str> = required_initializers
+        .iter()
+        .filter(|(_, initializer)| !initialized.contains(initializer))
+        .map(|(name, _)| *name)
+        .collect();
+    if missing_initializers.is_empty() {
+        Ok(())
+    } else {
+        Err(CreationError {
+            missing_initializers,
+        })
+    }
+}
+
+pub trait CreationContext {
+    // Requires every builder of T to call the initializer keyed by K, e.g.
+    // a person property, before it can be executed
+    fn require_initializer<T: Any, K: Any>(&mut self);
+}
+
+impl CreationContext for Context {
+    fn require_initializer<T: Any, K: Any>(&mut self) {
+        let data_container = self.get_or_init_data_container::<CreationPlugin>();
+        data_container
+            .required_initializers
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .insert(type_name::<K>(), TypeId::of::<K>());
+    }
+}
+
+pub struct CreationBuilder<'a, T: Copy + Clone + 'static> {
     context: &'a mut Context,
     creator: Box<CreatorFn<T>>,
     callbacks: Vec<Box<CreationCallback<T>>>,
     finalizer: Box<CreationCallback<T>>,
+    initialized: HashSet<TypeId>,
 }
 
-impl<'a, T: Copy + Clone> CreationBuilder<'a, T> {
+impl<'a, T: Copy + Clone + 'static> CreationBuilder<'a, T> {
     pub fn new(
         context: &'a mut Context,
         creator: impl FnOnce(&mut Context) -> T + 'static,
@@ -21,10 +101,13 @@ impl<'a, T: Copy + Clone> CreationBuilder<'a, T> {
             creator: Box::new(creator),
             callbacks: Vec::new(),
             finalizer: Box::new(finalizer),
+            initialized: HashSet::new(),
         }
     }
 
-    pub fn execute(self) -> T {
+    pub fn execute(self) -> Result<T, CreationError> {
+        check_required_initializers::<T>(self.context, &self.initialized)?;
+
         let creation = (self.creator)(self.context);
 
         // Perform the builder callbacks (if any)
@@ -34,10 +117,52 @@ impl<'a, T: Copy + Clone> CreationBuilder<'a, T> {
 
         (self.finalizer)(self.context, creation);
 
-        creation
+        Ok(creation)
     }
 
     pub fn add_callback(&mut self, callback: impl FnOnce(&mut Context, T) + 'static) {
         self.callbacks.push(Box::new(callback));
     }
+
+    // Adds a callback which satisfies the initializer requirement keyed by K
+    pub fn add_initializer<K: Any>(&mut self, callback: impl FnOnce(&mut Context, T) + 'static) {
+        self.initialized.insert(TypeId::of::<K>());
+        self.add_callback(callback);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::context::Context;
+    use crate::creation::{CreationBuilder, CreationContext, CreationError};
+    use std::any::type_name;
+
+    struct Name {}
+    struct Size {}
+
+    fn add_thing(context: &mut Context) -> CreationBuilder<'_, usize> {
+        CreationBuilder::new(context, |_| 0, |_, _| {})
+    }
+
+    #[test]
+    fn test_required_initializers() {
+        let mut context = Context::new();
+        assert_eq!(add_thing(&mut context).execute(), Ok(0));
+
+        context.require_initializer::<usize, Name>();
+        context.require_initializer::<usize, Size>();
+        let mut builder = add_thing(&mut context);
+        builder.add_callback(|_, _| {});
+        assert_eq!(
+            builder.execute(),
+            Err(CreationError {
+                missing_initializers: vec![type_name::<Name>(), type_name::<Size>()]
+            })
+        );
+
+        let mut builder = add_thing(&mut context);
+        builder.add_initializer::<Name>(|_, _| {});
+        builder.add_initializer::<Size>(|_, _| {});
+        assert_eq!(builder.execute(), Ok(0));
+    }
 }
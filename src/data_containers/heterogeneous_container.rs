@@ -1,5 +1,5 @@
 use std::any::Any;
-use std::any::TypeId;
+use std::any::{type_name, TypeId};
 use std::collections::HashMap;
 use std::rc::Rc;
 
@@ -7,6 +7,7 @@ use crate::data_containers::Property;
 
 pub struct HeterogeneousContainer {
     map: HashMap<TypeId, Rc<dyn Any>>,
+    names: HashMap<TypeId, &'static str>,
 }
 
 impl Default for HeterogeneousContainer {
@@ -19,11 +20,23 @@ impl HeterogeneousContainer {
     pub fn new() -> HeterogeneousContainer {
         HeterogeneousContainer {
             map: HashMap::new(),
+            names: HashMap::new(),
         }
     }
 
     pub fn set_value<K: Property>(&mut self, value: K::Value) {
         self.map.insert(TypeId::of::<K>(), Rc::new(value));
+        self.names.insert(TypeId::of::<K>(), type_name::<K>());
+    }
+
+    pub fn remove_value<K: Property>(&mut self) {
+        self.map.remove(&TypeId::of::<K>());
+        self.names.remove(&TypeId::of::<K>());
+    }
+
+    // Returns the type names of the properties with a value
+    pub fn get_property_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.names.values().copied()
     }
 
     pub fn get_value<K: Property>(&self) -> Option<&K::Value> {
@@ -40,6 +53,8 @@ impl HeterogeneousContainer {
     }
 }
 
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
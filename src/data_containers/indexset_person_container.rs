@@ -26,6 +26,14 @@ impl IndexSetPersonContainer {
             people: IndexSet::with_capacity_and_hasher(n, FxBuildHasher::default()),
         }
     }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.people.reserve(additional);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PersonId> + '_ {
+        self.people.iter().copied()
+    }
 }
 
 impl PersonContainer for IndexSetPersonContainer {
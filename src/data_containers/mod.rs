@@ -17,8 +17,10 @@ pub trait Property: Any {
     type Value: Any;
 }
 
+// Values only need to be Clone, so properties can hold data such as a
+// String or Vec. Copy values are still stored and read without overhead.
 pub trait PropertyWithDefault: Any {
-    type Value: Any + Copy;
+    type Value: Any + Clone;
     fn get_default() -> Self::Value;
 }
 
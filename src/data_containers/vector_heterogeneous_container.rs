@@ -1,10 +1,11 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
 
 use crate::data_containers::PropertyWithDefault;
 
 pub struct VecDataContainer {
     data: HashMap<TypeId, Box<dyn Any>>,
+    names: HashMap<TypeId, &'static str>,
 }
 
 impl Default for VecDataContainer {
@@ -17,37 +18,91 @@ impl VecDataContainer {
     pub fn new() -> VecDataContainer {
         VecDataContainer {
             data: HashMap::new(),
+            names: HashMap::new(),
         }
     }
 
-    pub fn set_value<K: PropertyWithDefault>(&mut self, index: usize, value: K::Value) {
-        let vec = self
-            .data
+    fn get_vec_mut<K: PropertyWithDefault>(&mut self) -> &mut Vec<K::Value> {
+        self.data
             .entry(TypeId::of::<K>())
-            .or_insert_with(|| Box::new(Vec::<K::Value>::with_capacity(index)));
-        let vec: &mut Vec<K::Value> = vec.downcast_mut().unwrap();
-        if index >= vec.len() {
-            vec.resize(index + 1, K::get_default());
+            .or_insert_with(|| Box::new(Vec::<K::Value>::new()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    // Records that a property has a stored value, which reserving room for
+    // values doesn't count as
+    fn set_name<K: PropertyWithDefault>(&mut self) {
+        self.names.insert(TypeId::of::<K>(), type_name::<K>());
+    }
+
+    // Returns the type names of the properties with stored values
+    pub fn get_property_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.names.values().copied()
+    }
+
+    pub fn set_value<K: PropertyWithDefault>(&mut self, index: usize, value: K::Value) {
+        self.set_name::<K>();
+        let vec = self.get_vec_mut::<K>();
+        // Only fill skipped indices with the default, which some properties
+        // don't have
+        if index > vec.len() {
+            vec.resize_with(index, K::get_default);
+        }
+        if index == vec.len() {
+            vec.push(value);
+        } else {
+            vec[index] = value;
         }
-        vec[index] = value;
+    }
+
+    // Sets the values for indices start..start + values.len()
+    pub fn set_value_range<K: PropertyWithDefault>(&mut self, start: usize, values: Vec<K::Value>) {
+        self.set_name::<K>();
+        let vec = self.get_vec_mut::<K>();
+        // As in set_value, the default is only used for skipped indices
+        if start > vec.len() {
+            vec.resize_with(start, K::get_default);
+        }
+        let end = vec.len().min(start + values.len());
+        vec.splice(start..end, values);
+    }
+
+    // Makes room for len values of a property without reallocating
+    pub fn reserve<K: PropertyWithDefault>(&mut self, len: usize) {
+        let vec = self.get_vec_mut::<K>();
+        vec.reserve(len.saturating_sub(vec.len()));
+    }
+
+    // Returns the stored values for a property, which may be shorter than
+    // the number of people if later values have never been set
+    pub fn get_values<K: PropertyWithDefault>(&self) -> Option<&Vec<K::Value>> {
+        self.data
+            .get(&TypeId::of::<K>())
+            .map(|boxed_vec| boxed_vec.downcast_ref::<Vec<K::Value>>().unwrap())
+    }
+
+    pub fn set_values<K: PropertyWithDefault>(&mut self, values: Vec<K::Value>) {
+        self.set_name::<K>();
+        self.data.insert(TypeId::of::<K>(), Box::new(values));
     }
 
     pub fn get_value<K: PropertyWithDefault>(&self, index: usize) -> K::Value {
-        match self.data.get(&TypeId::of::<K>()) {
-            Some(boxed_vec) => {
-                let vec = boxed_vec.downcast_ref::<Vec<K::Value>>().unwrap();
-                if index >= vec.len() {
-                    K::get_default()
-                } else {
-                    vec[index]
-                }
-            }
+        match self.get_value_ref::<K>(index) {
+            Some(value) => value.clone(),
             None => K::get_default(),
         }
     }
+
+    // Returns a reference to the stored value, or None if the value has
+    // never been set and is the default
+    pub fn get_value_ref<K: PropertyWithDefault>(&self, index: usize) -> Option<&K::Value> {
+        self.get_values::<K>()?.get(index)
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -69,6 +124,40 @@ mod tests {
         }
     }
 
+    // Like a person property with an initializer, which has no default
+    struct KeyWithoutDefault {}
+    impl PropertyWithDefault for KeyWithoutDefault {
+        type Value = usize;
+
+        fn get_default() -> <Self as PropertyWithDefault>::Value {
+            panic!("KeyWithoutDefault has no default")
+        }
+    }
+
+    #[test]
+    fn test_without_default() {
+        let mut container = VecDataContainer::new();
+        container.set_value::<KeyWithoutDefault>(0, 1);
+        container.set_value_range::<KeyWithoutDefault>(1, vec![2, 3]);
+        container.set_value_range::<KeyWithoutDefault>(0, vec![4]);
+        assert_eq!(
+            container.get_values::<KeyWithoutDefault>(),
+            Some(&vec![4, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_property_names() {
+        let mut container = VecDataContainer::new();
+        container.reserve::<KeyOne>(10);
+        assert_eq!(container.get_property_names().count(), 0);
+        container.set_value::<KeyOne>(0, 1);
+        container.set_value_range::<KeyTwo>(0, vec![true]);
+        let mut names: Vec<&str> = container.get_property_names().collect();
+        names.sort();
+        assert_eq!(names, vec![type_name::<KeyOne>(), type_name::<KeyTwo>()]);
+    }
+
     #[test]
     fn test() {
         use super::*;
@@ -92,5 +181,17 @@ mod tests {
 
         container.set_value::<KeyOne>(1, 3);
         assert_eq!(container.get_value::<KeyOne>(1), 3);
+
+        container.set_value_range::<KeyOne>(2, vec![4, 5, 6]);
+        assert_eq!(container.get_value::<KeyOne>(1), 3);
+        assert_eq!(container.get_value::<KeyOne>(2), 4);
+        assert_eq!(container.get_value::<KeyOne>(4), 6);
+        container.set_value_range::<KeyOne>(7, vec![7]);
+        assert_eq!(container.get_value::<KeyOne>(5), 0);
+        assert_eq!(container.get_value::<KeyOne>(7), 7);
+
+        container.reserve::<KeyTwo>(100);
+        assert!(container.get_values::<KeyTwo>().unwrap().capacity() >= 100);
+        assert!(container.get_value::<KeyTwo>(2));
     }
 }
@@ -0,0 +1,101 @@
+use crate::context::Context;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type EventCallback<E> = dyn Fn(&mut Context, &E);
+
+struct EventsDataContainer {
+    // Maps TypeId::of::<E>() to Vec<Rc<EventCallback<E>>>
+    event_subscribers: HashMap<TypeId, Box<dyn Any>>,
+}
+
+crate::context::define_plugin!(
+    EventsPlugin,
+    EventsDataContainer,
+    EventsDataContainer {
+        event_subscribers: HashMap::new(),
+    }
+);
+
+pub trait EventContext {
+    fn subscribe_to_event<E: Any>(&mut self, callback: impl Fn(&mut Context, &E) + 'static);
+
+    fn emit_event<E: Any>(&mut self, event: E);
+}
+
+impl EventContext for Context {
+    fn subscribe_to_event<E: Any>(&mut self, callback: impl Fn(&mut Context, &E) + 'static) {
+        let data_container = self.get_or_init_data_container::<EventsPlugin>();
+        let callback_vec = data_container
+            .event_subscribers
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::<Vec<Rc<EventCallback<E>>>>::default());
+        let callback_vec: &mut Vec<Rc<EventCallback<E>>> = callback_vec.downcast_mut().unwrap();
+        callback_vec.push(Rc::new(callback));
+    }
+
+    fn emit_event<E: Any>(&mut self, event: E) {
+        // If data container is not loaded then there are no subscribers
+        let mut callbacks_to_add = Vec::<Box<dyn FnOnce(&mut Context)>>::new();
+        if let Some(data_container) = self.get_data_container::<EventsPlugin>() {
+            if let Some(callback_vec) = data_container.event_subscribers.get(&TypeId::of::<E>()) {
+                let callback_vec: &Vec<Rc<EventCallback<E>>> = callback_vec.downcast_ref().unwrap();
+                let event = Rc::new(event);
+                for callback in callback_vec {
+                    let internal_callback = Rc::clone(callback);
+                    let internal_event = Rc::clone(&event);
+                    callbacks_to_add.push(Box::new(move |context| {
+                        (*internal_callback)(context, &internal_event)
+                    }));
+                }
+            }
+        }
+        for callback in callbacks_to_add {
+            self.queue_callback(callback);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::context::Context;
+    use crate::events::EventContext;
+    use crate::people::PersonId;
+
+    struct PersonInfected {
+        person_id: PersonId,
+    }
+
+    crate::context::define_plugin!(InfectionLog, Vec<(f64, PersonId)>, Vec::new());
+
+    #[test]
+    fn test() {
+        let mut context = Context::new();
+        // Emitting without subscribers does nothing
+        context.emit_event(PersonInfected {
+            person_id: PersonId::new(0),
+        });
+
+        for _ in 0..2 {
+            context.subscribe_to_event::<PersonInfected>(|context, event| {
+                let time = context.get_time();
+                context
+                    .get_or_init_data_container::<InfectionLog>()
+                    .push((time, event.person_id));
+            });
+        }
+        context.add_plan(1.0, |context| {
+            context.emit_event(PersonInfected {
+                person_id: PersonId::new(1),
+            });
+            // Subscribers are called after the current callback completes
+            assert!(context.get_data_container::<InfectionLog>().is_none());
+        });
+        context.execute();
+        assert_eq!(
+            *context.get_or_init_data_container::<InfectionLog>(),
+            vec![(1.0, PersonId::new(1)), (1.0, PersonId::new(1))]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use crate::context::Context;
+use crate::random::RandomContext;
+use crate::reports::{Report, ReportsContext};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Identifies the replicate that released a report item. It is written
+// before the item's own columns in each report file.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ReplicateId {
+    pub scenario: usize,
+    pub replicate: usize,
+    pub seed: u64,
+}
+
+// The rows one replicate released for a report, kept until every
+// replicate has finished so files are written in replicate order
+struct ReportBuffer {
+    writer: csv::Writer<Vec<u8>>,
+    // The first error serializing an item, returned once the replicate ends
+    error: Option<csv::Error>,
+}
+
+impl ReportBuffer {
+    fn into_rows(self) -> io::Result<Vec<u8>> {
+        if let Some(e) = self.error {
+            return Err(e.into());
+        }
+        self.writer.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+type ReportRegistration = fn(&mut Context, ReplicateId) -> Rc<RefCell<ReportBuffer>>;
+
+struct ExperimentReport {
+    path: PathBuf,
+    registration: ReportRegistration,
+}
+
+fn register_report<T: Report>(context: &mut Context, id: ReplicateId) -> Rc<RefCell<ReportBuffer>>
+where
+    T::Item: Serialize,
+{
+    let buffer = Rc::new(RefCell::new(ReportBuffer {
+        writer: csv::Writer::from_writer(Vec::new()),
+        error: None,
+    }));
+    let handler_buffer = Rc::clone(&buffer);
+    context.set_report_item_handler::<T>(move |item| {
+        let mut buffer = handler_buffer.borrow_mut();
+        if buffer.error.is_none() {
+            if let Err(e) = buffer.writer.serialize((id, item)) {
+                buffer.error = Some(e);
+            }
+        }
+    });
+    buffer
+}
+
+// Runs one replicate, returning the buffered rows of each report
+fn run_replicate<P>(
+    experiment: &Experiment<P>,
+    id: ReplicateId,
+    setup: &impl Fn(&mut Context, &P),
+) -> io::Result<Vec<Vec<u8>>> {
+    let mut context = Context::new();
+    context.set_base_random_seed(id.seed);
+    let buffers: Vec<Rc<RefCell<ReportBuffer>>> = experiment
+        .reports
+        .iter()
+        .map(|report| (report.registration)(&mut context, id))
+        .collect();
+    setup(&mut context, &experiment.scenarios[id.scenario]);
+    context.execute();
+    drop(context);
+    buffers
+        .into_iter()
+        .map(|buffer| match Rc::try_unwrap(buffer) {
+            Ok(buffer) => buffer.into_inner().into_rows(),
+            Err(_) => unreachable!("The report handler is dropped with the Context"),
+        })
+        .collect()
+}
+
+// Appends the buffered rows of each replicate to a report file, writing
+// the header once
+fn write_report(writer: &mut csv::Writer<File>, replicate_rows: &[&[u8]]) -> io::Result<()> {
+    let mut has_header = false;
+    for rows in replicate_rows {
+        let mut reader = csv::Reader::from_reader(*rows);
+        let header = reader.byte_headers()?.clone();
+        if !has_header && !header.is_empty() {
+            writer.write_byte_record(&header)?;
+            has_header = true;
+        }
+        for record in reader.byte_records() {
+            writer.write_byte_record(&record?)?;
+        }
+    }
+    writer.flush()
+}
+
+// Runs every scenario once for each seed, spreading the replicates
+// across a pool of threads
+pub struct Experiment<P> {
+    scenarios: Vec<P>,
+    seeds: Vec<u64>,
+    num_threads: usize,
+    reports: Vec<ExperimentReport>,
+}
+
+impl<P: Sync> Experiment<P> {
+    pub fn new(scenarios: Vec<P>) -> Experiment<P> {
+        Experiment {
+            scenarios,
+            seeds: vec![0],
+            num_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            reports: Vec::new(),
+        }
+    }
+
+    pub fn set_seeds(mut self, seeds: Vec<u64>) -> Experiment<P> {
+        self.seeds = seeds;
+        self
+    }
+
+    pub fn set_num_threads(mut self, num_threads: usize) -> Experiment<P> {
+        assert!(num_threads > 0, "Experiment needs at least one thread");
+        self.num_threads = num_threads;
+        self
+    }
+
+    // Writes the items of report T from every replicate to a single file
+    pub fn add_report<T: Report>(mut self, path: impl AsRef<Path>) -> Experiment<P>
+    where
+        T::Item: Serialize,
+    {
+        self.reports.push(ExperimentReport {
+            path: path.as_ref().to_path_buf(),
+            registration: register_report::<T>,
+        });
+        self
+    }
+
+    pub fn get_replicate_ids(&self) -> Vec<ReplicateId> {
+        let mut replicate_ids = Vec::new();
+        for scenario in 0..self.scenarios.len() {
+            for (replicate, seed) in self.seeds.iter().enumerate() {
+                replicate_ids.push(ReplicateId {
+                    scenario,
+                    replicate,
+                    seed: *seed,
+                });
+            }
+        }
+        replicate_ids
+    }
+
+    // Runs each replicate in a new Context. The base random seed and the
+    // report handlers are set before setup is called with the scenario,
+    // and the Context is executed once setup returns.
+    pub fn run(&self, setup: impl Fn(&mut Context, &P) + Sync) -> io::Result<()> {
+        let mut writers = Vec::new();
+        for report in &self.reports {
+            writers.push(csv::Writer::from_writer(File::create(&report.path)?));
+        }
+
+        let replicate_ids = self.get_replicate_ids();
+        let next_replicate = AtomicUsize::new(0);
+        let mut results: Vec<(usize, io::Result<Vec<Vec<u8>>>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.num_threads.min(replicate_ids.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        loop {
+                            let index = next_replicate.fetch_add(1, Ordering::Relaxed);
+                            let Some(id) = replicate_ids.get(index) else {
+                                break;
+                            };
+                            results.push((index, run_replicate(self, *id, &setup)));
+                        }
+                        results
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        // Rows are written in replicate order, whichever thread ran them
+        results.sort_by_key(|(index, _)| *index);
+        let mut replicate_rows = Vec::new();
+        for (_, result) in results {
+            replicate_rows.push(result?);
+        }
+        for (report_index, writer) in writers.iter_mut().enumerate() {
+            let rows: Vec<&[u8]> = replicate_rows
+                .iter()
+                .map(|rows| rows[report_index].as_slice())
+                .collect();
+            write_report(writer, &rows)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::context::Context;
+    use crate::experiment::Experiment;
+    use crate::random::RandomContext;
+    use crate::reports::{Report, ReportsContext};
+    use rand::Rng;
+    use serde_derive::Serialize;
+    use std::fs;
+
+    crate::define_random_id!(DrawRng);
+
+    crate::context::define_plugin!(DrawReport, (), ());
+
+    #[derive(Serialize)]
+    struct DrawItem {
+        time: f64,
+        draw: u32,
+    }
+
+    impl Report for DrawReport {
+        type Item = DrawItem;
+    }
+
+    fn setup(context: &mut Context, end_time: &f64) {
+        let end_time = *end_time;
+        context.add_plan(end_time, move |context| {
+            let draw = context.get_rng::<DrawRng>().gen_range(0..1000);
+            context.release_report_item::<DrawReport>(DrawItem {
+                time: end_time,
+                draw,
+            });
+        });
+    }
+
+    fn run_experiment(num_threads: usize) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("draws.csv");
+        Experiment::new(vec![1.0, 2.0])
+            .set_seeds(vec![8675309, 42, 8675309])
+            .set_num_threads(num_threads)
+            .add_report::<DrawReport>(&path)
+            .run(setup)
+            .unwrap();
+        let output = fs::read_to_string(&path).unwrap();
+        output.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test() {
+        let lines = run_experiment(3);
+        assert_eq!(lines[0], "scenario,replicate,seed,time,draw");
+        assert_eq!(lines.len(), 7);
+        let rows: Vec<Vec<&str>> = lines[1..]
+            .iter()
+            .map(|line| line.split(',').collect())
+            .collect();
+        // Rows are in replicate order
+        let ids: Vec<(&str, &str)> = rows.iter().map(|row| (row[0], row[1])).collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("0", "0"),
+                ("0", "1"),
+                ("0", "2"),
+                ("1", "0"),
+                ("1", "1"),
+                ("1", "2")
+            ]
+        );
+        for scenario in 0..2 {
+            let scenario_rows: Vec<&Vec<&str>> = rows
+                .iter()
+                .filter(|row| row[0] == scenario.to_string())
+                .collect();
+            assert_eq!(scenario_rows.len(), 3);
+            // Replicates with the same seed produce the same draw
+            assert_eq!(scenario_rows[0][4], scenario_rows[2][4]);
+            assert_ne!(scenario_rows[0][4], scenario_rows[1][4]);
+        }
+
+        // Results don't depend on the number of threads
+        assert_eq!(run_experiment(1), lines);
+    }
+
+    crate::context::define_plugin!(NestedReport, (), ());
+
+    #[derive(Serialize)]
+    struct NestedItem {
+        draws: Vec<u32>,
+    }
+
+    impl Report for NestedReport {
+        type Item = NestedItem;
+    }
+
+    #[test]
+    fn test_report_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = Experiment::new(vec![()])
+            .set_num_threads(2)
+            .add_report::<NestedReport>(dir.path().join("nested.csv"))
+            .run(|context, _| {
+                context.add_plan(1.0, |context| {
+                    // csv can't write a sequence inside a row
+                    context.release_report_item::<NestedReport>(NestedItem { draws: vec![1, 2] });
+                });
+            });
+        assert!(result.is_err());
+    }
+}
@@ -1,14 +1,37 @@
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
 use std::rc::Rc;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::checkpoint::CheckpointContext;
 use crate::context::Context;
 use crate::data_containers::heterogeneous_container::HeterogeneousContainer;
 use crate::data_containers::Property;
 
-// TODO: Decide if we want default global property values
-pub trait GlobalProperty: Property {}
+pub trait GlobalProperty: Property {
+    // The value read when the property has not been set
+    fn get_default() -> Option<&'static Self::Value> {
+        None
+    }
+}
+
+// A global property which always has a value
+pub trait GlobalPropertyWithDefault: GlobalProperty {
+    fn get_default_value() -> &'static Self::Value;
+}
 
+// Defines a global property, optionally with a default value. The default
+// is computed once, stored in a static, and shared by every Context in
+// the process, so a property with a default needs a Send + Sync value.
+// Properties without a default have no such bound.
 #[macro_export]
 macro_rules! define_global_property {
     ($global_property:ident, $value:ty) => {
@@ -20,15 +43,171 @@ macro_rules! define_global_property {
 
         impl $crate::global_properties::GlobalProperty for $global_property {}
     };
+    ($global_property:ident, $value:ty, $default:expr) => {
+        pub struct $global_property {}
+
+        impl $crate::data_containers::Property for $global_property {
+            type Value = $value;
+        }
+
+        impl $crate::global_properties::GlobalProperty for $global_property {
+            fn get_default() -> Option<&'static Self::Value> {
+                Some(<Self as $crate::global_properties::GlobalPropertyWithDefault>::get_default_value())
+            }
+        }
+
+        impl $crate::global_properties::GlobalPropertyWithDefault for $global_property {
+            fn get_default_value() -> &'static Self::Value {
+                static DEFAULT: std::sync::OnceLock<$value> = std::sync::OnceLock::new();
+                DEFAULT.get_or_init(|| $default)
+            }
+        }
+    };
 }
 pub use define_global_property;
 
+#[derive(Debug)]
+pub enum GlobalPropertyLoadError {
+    Io(io::Error),
+    // The parameters are not a JSON object
+    Parse(serde_json::Error),
+    // The parameters are not a CSV file of name,value rows
+    Csv(csv::Error),
+    // A value could not be read as the type of its property
+    InvalidValue(String, serde_json::Error),
+    // Names which have not been registered
+    UnknownProperties(Vec<String>),
+    // Registered properties which have no value
+    MissingProperties(Vec<String>),
+    // Properties marked with require_global_property which have no value
+    MissingRequiredProperties(Vec<String>),
+    // Properties which can't be set because the simulation has started
+    ImmutableProperties(Vec<String>),
+}
+
+impl fmt::Display for GlobalPropertyLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlobalPropertyLoadError::Io(e) => write!(f, "Parameters IO error: {}", e),
+            GlobalPropertyLoadError::Parse(e) => write!(f, "Invalid parameters: {}", e),
+            GlobalPropertyLoadError::Csv(e) => write!(f, "Invalid parameters: {}", e),
+            GlobalPropertyLoadError::InvalidValue(name, e) => {
+                write!(f, "Invalid value for global property {}: {}", name, e)
+            }
+            GlobalPropertyLoadError::UnknownProperties(names) => {
+                write!(f, "Unknown global properties: {}", names.join(", "))
+            }
+            GlobalPropertyLoadError::MissingProperties(names) => {
+                write!(f, "Missing global properties: {}", names.join(", "))
+            }
+            GlobalPropertyLoadError::MissingRequiredProperties(names) => {
+                write!(
+                    f,
+                    "Missing required global properties: {}",
+                    names.join(", ")
+                )
+            }
+            GlobalPropertyLoadError::ImmutableProperties(names) => write!(
+                f,
+                "Global properties are immutable after the simulation starts: {}",
+                names.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GlobalPropertyLoadError {}
+
+impl From<io::Error> for GlobalPropertyLoadError {
+    fn from(e: io::Error) -> Self {
+        GlobalPropertyLoadError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for GlobalPropertyLoadError {
+    fn from(e: serde_json::Error) -> Self {
+        GlobalPropertyLoadError::Parse(e)
+    }
+}
+
+impl From<csv::Error> for GlobalPropertyLoadError {
+    fn from(e: csv::Error) -> Self {
+        GlobalPropertyLoadError::Csv(e)
+    }
+}
+
+// Reads parameters from a file. Files ending in .csv have a name,value
+// header and one row per property, where each value is read as JSON or,
+// failing that, as a string. Any other file is read as a JSON object.
+// TOML isn't supported.
+pub fn read_parameters_file(
+    path: impl AsRef<Path>,
+) -> Result<Map<String, Value>, GlobalPropertyLoadError> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    if path.extension().is_some_and(|extension| extension == "csv") {
+        read_parameters_csv(reader)
+    } else {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+fn read_parameters_csv(
+    reader: impl io::Read,
+) -> Result<Map<String, Value>, GlobalPropertyLoadError> {
+    let mut parameters = Map::new();
+    for record in csv::Reader::from_reader(reader).records() {
+        let record = record?;
+        let value = serde_json::from_str(&record[1])
+            .unwrap_or_else(|_| Value::String(record[1].to_string()));
+        parameters.insert(record[0].to_string(), value);
+    }
+    Ok(parameters)
+}
+
+struct GlobalPropertyRegistration {
+    type_id: TypeId,
+    parse: fn(Value) -> Result<Box<dyn Any>, serde_json::Error>,
+    set: fn(&mut Context, Box<dyn Any>),
+    is_set: fn(&Context) -> bool,
+}
+
+fn parse_global_property<T: GlobalProperty>(value: Value) -> Result<Box<dyn Any>, serde_json::Error>
+where
+    T::Value: DeserializeOwned,
+{
+    let value: T::Value = serde_json::from_value(value)?;
+    Ok(Box::new(value))
+}
+
+fn set_global_property<T: GlobalProperty>(context: &mut Context, value: Box<dyn Any>) {
+    context.set_global_property_value::<T>(*value.downcast::<T::Value>().unwrap());
+}
+
+fn is_global_property_set<T: GlobalProperty>(context: &Context) -> bool {
+    context.get_global_property_value::<T>().is_some()
+}
+
 struct GlobalPropertyDataContainer {
     global_property_container: HeterogeneousContainer,
     global_property_change_callbacks: HashMap<TypeId, Box<dyn Any>>,
+    // Maps the names used in parameter files to properties
+    global_property_registrations: HashMap<String, GlobalPropertyRegistration>,
+    required_global_properties: BTreeMap<&'static str, fn(&Context) -> bool>,
+    // Whether the before start callback checking required properties has
+    // been added
+    validation_added: bool,
+    immutable_global_properties: HashSet<TypeId>,
 }
 
-type GlobalPropertyChangeCallback<T> = dyn Fn(&mut Context, Option<&T>);
+// Passed to observers when a global property is set. previous is None
+// if the property had no value.
+pub struct GlobalPropertyChangeEvent<'a, T: GlobalProperty> {
+    pub previous: Option<&'a T::Value>,
+    pub current: &'a T::Value,
+}
+
+type GlobalPropertyChangeCallback<T> = dyn Fn(&mut Context, GlobalPropertyChangeEvent<T>);
 
 crate::context::define_plugin!(
     GlobalPropertyPlugin,
@@ -36,92 +215,324 @@ crate::context::define_plugin!(
     GlobalPropertyDataContainer {
         global_property_container: HeterogeneousContainer::new(),
         global_property_change_callbacks: HashMap::new(),
+        global_property_registrations: HashMap::new(),
+        required_global_properties: BTreeMap::new(),
+        validation_added: false,
+        immutable_global_properties: HashSet::new(),
     }
 );
 
+// Returns the checkpoint sections needed to save every property with a
+// value
+pub(crate) fn get_required_checkpoint_sections(context: &Context) -> Vec<&'static str> {
+    match context.get_data_container::<GlobalPropertyPlugin>() {
+        None => Vec::new(),
+        Some(data_container) => data_container
+            .global_property_container
+            .get_property_names()
+            .collect(),
+    }
+}
+
 pub trait GlobalPropertyContext {
+    // Returns the value of T, or its default if it has not been set
     fn get_global_property_value<T: GlobalProperty>(&self) -> Option<&T::Value>;
 
+    fn get_global_property_value_or_default<T: GlobalPropertyWithDefault>(&self) -> &T::Value;
+
     fn set_global_property_value<T: GlobalProperty>(&mut self, value: T::Value);
 
     fn observe_global_property_changes<T: GlobalProperty>(
         &mut self,
-        callback: impl Fn(&mut Context, Option<&T::Value>) + 'static,
+        callback: impl Fn(&mut Context, GlobalPropertyChangeEvent<T>) + 'static,
     );
+
+    // Saves the value of global property T in checkpoints
+    fn register_checkpoint_global_property<T: GlobalProperty>(&mut self)
+    where
+        T::Value: Serialize + DeserializeOwned + Clone;
+
+    // Registers global property T under a name used in parameter files
+    fn register_global_property<T: GlobalProperty>(&mut self, name: &str)
+    where
+        T::Value: DeserializeOwned;
+
+    // Sets the named global properties. No values are set unless every
+    // name is registered, every value is valid, every registered property
+    // has a value afterwards, and none of the named properties is immutable
+    // after a start which has already happened.
+    fn set_global_properties(
+        &mut self,
+        parameters: &Map<String, Value>,
+    ) -> Result<(), GlobalPropertyLoadError>;
+
+    fn load_global_properties(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GlobalPropertyLoadError>;
+
+    // Marks T as required. Execution panics on start if a required
+    // property has no value, and requiring a property without a value
+    // after the start panics immediately.
+    fn require_global_property<T: GlobalProperty>(&mut self);
+
+    // Checks that every required global property has a value, listing
+    // the ones which don't
+    fn validate_required_global_properties(&self) -> Result<(), GlobalPropertyLoadError>;
+
+    // Setting T once the simulation has started will panic
+    fn make_global_property_immutable_after_start<T: GlobalProperty>(&mut self);
 }
 
 impl GlobalPropertyContext for Context {
     fn get_global_property_value<T: GlobalProperty>(&self) -> Option<&T::Value> {
         let data_container = self.get_data_container::<GlobalPropertyPlugin>();
-        return match data_container {
-            None => None,
-            Some(data_container) => data_container.global_property_container.get_value::<T>(),
-        };
+        match data_container {
+            None => T::get_default(),
+            Some(data_container) => data_container
+                .global_property_container
+                .get_value::<T>()
+                .or_else(|| T::get_default()),
+        }
+    }
+
+    fn get_global_property_value_or_default<T: GlobalPropertyWithDefault>(&self) -> &T::Value {
+        self.get_data_container::<GlobalPropertyPlugin>()
+            .and_then(|data_container| data_container.global_property_container.get_value::<T>())
+            .unwrap_or_else(|| T::get_default_value())
     }
 
     fn set_global_property_value<T: GlobalProperty>(&mut self, value: T::Value) {
-        // First look for callbacks
+        let started = self.has_started();
+        let data_container = self.get_or_init_data_container::<GlobalPropertyPlugin>();
+        if started
+            && data_container
+                .immutable_global_properties
+                .contains(&TypeId::of::<T>())
+        {
+            panic!(
+                "Global property {} is immutable after the simulation starts",
+                std::any::type_name::<T>()
+            );
+        }
+        let previous_value = data_container.global_property_container.get_rc_value::<T>();
+        data_container
+            .global_property_container
+            .set_value::<T>(value);
+
+        // Observation callbacks
         let mut callbacks_to_add = Vec::<Box<dyn FnOnce(&mut Context)>>::new();
-        let data_container = self.get_data_container::<GlobalPropertyPlugin>();
-        if data_container.is_some() {
-            let data_container = data_container.unwrap();
-            // Observation callbacks
-            let callback_vec = data_container
-                .global_property_change_callbacks
-                .get(&TypeId::of::<T>());
-            if callback_vec.is_some() {
-                let callback_vec: &Vec<Rc<GlobalPropertyChangeCallback<T::Value>>> =
-                    callback_vec.unwrap().downcast_ref().unwrap();
-                if !callback_vec.is_empty() {
-                    let current_value =
-                        data_container.global_property_container.get_rc_value::<T>();
-                    for callback in callback_vec {
-                        let internal_callback = Rc::clone(callback);
-                        match &current_value {
-                            None => callbacks_to_add
-                                .push(Box::new(move |context| (*internal_callback)(context, None))),
-                            Some(current_value) => {
-                                let current_value_clone = Rc::clone(current_value);
-                                callbacks_to_add.push(Box::new(move |context| {
-                                    (*internal_callback)(context, Some(&*current_value_clone))
-                                }));
-                            }
-                        }
-                    }
+        let callback_vec = data_container
+            .global_property_change_callbacks
+            .get(&TypeId::of::<T>());
+        if let Some(callback_vec) = callback_vec {
+            let callback_vec: &Vec<Rc<GlobalPropertyChangeCallback<T>>> =
+                callback_vec.downcast_ref().unwrap();
+            if !callback_vec.is_empty() {
+                let current_value = data_container
+                    .global_property_container
+                    .get_rc_value::<T>()
+                    .unwrap();
+                for callback in callback_vec {
+                    let internal_callback = Rc::clone(callback);
+                    let previous_value = previous_value.clone();
+                    let current_value = Rc::clone(&current_value);
+                    callbacks_to_add.push(Box::new(move |context| {
+                        (*internal_callback)(
+                            context,
+                            GlobalPropertyChangeEvent {
+                                previous: previous_value.as_deref().or_else(|| T::get_default()),
+                                current: &current_value,
+                            },
+                        )
+                    }));
                 }
             }
         }
         for callback in callbacks_to_add {
             self.queue_callback(callback);
         }
-        let data_container = self.get_data_container_mut::<GlobalPropertyPlugin>();
-        data_container
-            .global_property_container
-            .set_value::<T>(value);
     }
 
     fn observe_global_property_changes<T: GlobalProperty>(
         &mut self,
-        callback: impl Fn(&mut Context, Option<&T::Value>) + 'static,
+        callback: impl Fn(&mut Context, GlobalPropertyChangeEvent<T>) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<GlobalPropertyPlugin>();
+        let data_container = self.get_or_init_data_container::<GlobalPropertyPlugin>();
         let callback_vec = data_container
             .global_property_change_callbacks
             .entry(TypeId::of::<T>())
             .or_insert_with(|| {
-                let new_vec = Vec::<Rc<GlobalPropertyChangeCallback<T::Value>>>::new();
+                let new_vec = Vec::<Rc<GlobalPropertyChangeCallback<T>>>::new();
                 Box::new(new_vec)
             });
-        let callback_vec: &mut Vec<Rc<GlobalPropertyChangeCallback<T::Value>>> =
+        let callback_vec: &mut Vec<Rc<GlobalPropertyChangeCallback<T>>> =
             callback_vec.downcast_mut().unwrap();
         callback_vec.push(Rc::new(callback));
     }
+
+    fn register_checkpoint_global_property<T: GlobalProperty>(&mut self)
+    where
+        T::Value: Serialize + DeserializeOwned + Clone,
+    {
+        self.add_checkpoint_section::<Option<T::Value>>(
+            std::any::type_name::<T>(),
+            |context| context.get_global_property_value::<T>().cloned(),
+            |context, value| {
+                let data_container = context.get_or_init_data_container::<GlobalPropertyPlugin>();
+                match value {
+                    None => data_container.global_property_container.remove_value::<T>(),
+                    Some(value) => data_container
+                        .global_property_container
+                        .set_value::<T>(value),
+                }
+            },
+        );
+    }
+
+    fn register_global_property<T: GlobalProperty>(&mut self, name: &str)
+    where
+        T::Value: DeserializeOwned,
+    {
+        let data_container = self.get_or_init_data_container::<GlobalPropertyPlugin>();
+        data_container.global_property_registrations.insert(
+            name.to_string(),
+            GlobalPropertyRegistration {
+                type_id: TypeId::of::<T>(),
+                parse: parse_global_property::<T>,
+                set: set_global_property::<T>,
+                is_set: is_global_property_set::<T>,
+            },
+        );
+    }
+
+    fn set_global_properties(
+        &mut self,
+        parameters: &Map<String, Value>,
+    ) -> Result<(), GlobalPropertyLoadError> {
+        let no_registrations = HashMap::new();
+        let no_immutable_properties = HashSet::new();
+        let (registrations, immutable_properties) =
+            match self.get_data_container::<GlobalPropertyPlugin>() {
+                None => (&no_registrations, &no_immutable_properties),
+                Some(data_container) => (
+                    &data_container.global_property_registrations,
+                    &data_container.immutable_global_properties,
+                ),
+            };
+
+        let unknown_properties: Vec<String> = parameters
+            .keys()
+            .filter(|name| !registrations.contains_key(*name))
+            .cloned()
+            .collect();
+        if !unknown_properties.is_empty() {
+            return Err(GlobalPropertyLoadError::UnknownProperties(
+                unknown_properties,
+            ));
+        }
+        let mut missing_properties: Vec<String> = registrations
+            .iter()
+            .filter(|(name, registration)| {
+                !parameters.contains_key(*name) && !(registration.is_set)(self)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !missing_properties.is_empty() {
+            missing_properties.sort();
+            return Err(GlobalPropertyLoadError::MissingProperties(
+                missing_properties,
+            ));
+        }
+        if self.has_started() {
+            let mut immutable_names: Vec<String> = parameters
+                .keys()
+                .filter(|name| immutable_properties.contains(&registrations[*name].type_id))
+                .cloned()
+                .collect();
+            if !immutable_names.is_empty() {
+                immutable_names.sort();
+                return Err(GlobalPropertyLoadError::ImmutableProperties(
+                    immutable_names,
+                ));
+            }
+        }
+
+        let mut values = Vec::new();
+        for (name, value) in parameters {
+            let registration = &registrations[name];
+            let value = (registration.parse)(value.clone())
+                .map_err(|e| GlobalPropertyLoadError::InvalidValue(name.clone(), e))?;
+            values.push((registration.set, value));
+        }
+        for (set, value) in values {
+            set(self, value);
+        }
+        Ok(())
+    }
+
+    fn load_global_properties(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), GlobalPropertyLoadError> {
+        let parameters = read_parameters_file(path)?;
+        self.set_global_properties(&parameters)
+    }
+
+    fn require_global_property<T: GlobalProperty>(&mut self) {
+        let data_container = self.get_or_init_data_container::<GlobalPropertyPlugin>();
+        data_container
+            .required_global_properties
+            .insert(std::any::type_name::<T>(), is_global_property_set::<T>);
+        let validation_added = std::mem::replace(&mut data_container.validation_added, true);
+        // Requirements are checked together on start, or straight away if
+        // the simulation has already started
+        if self.has_started() {
+            if let Err(e) = self.validate_required_global_properties() {
+                panic!("{}", e);
+            }
+        } else if !validation_added {
+            self.add_before_start_callback(|context| {
+                if let Err(e) = context.validate_required_global_properties() {
+                    panic!("{}", e);
+                }
+            });
+        }
+    }
+
+    fn make_global_property_immutable_after_start<T: GlobalProperty>(&mut self) {
+        let data_container = self.get_or_init_data_container::<GlobalPropertyPlugin>();
+        data_container
+            .immutable_global_properties
+            .insert(TypeId::of::<T>());
+    }
+
+    fn validate_required_global_properties(&self) -> Result<(), GlobalPropertyLoadError> {
+        let missing_properties: Vec<String> =
+            match self.get_data_container::<GlobalPropertyPlugin>() {
+                None => Vec::new(),
+                Some(data_container) => data_container
+                    .required_global_properties
+                    .iter()
+                    .filter(|(_, is_set)| !is_set(self))
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            };
+        if missing_properties.is_empty() {
+            Ok(())
+        } else {
+            Err(GlobalPropertyLoadError::MissingRequiredProperties(
+                missing_properties,
+            ))
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::context::Context;
-    use crate::global_properties::GlobalPropertyContext;
+    use crate::global_properties::{GlobalPropertyContext, GlobalPropertyLoadError};
+    use serde_json::json;
 
     define_global_property!(PropertyA, usize);
 
@@ -141,11 +552,15 @@ mod test {
             number_of_calls: 0,
         });
         context.set_global_property_value::<PropertyA>(1);
-        context.observe_global_property_changes::<PropertyA>(|context, old_value| {
+        context.observe_global_property_changes::<PropertyA>(|context, event| {
             let current_b_value = context.get_global_property_value::<PropertyB>().unwrap();
             let number_of_calls = current_b_value.number_of_calls;
+            assert_eq!(
+                *event.current,
+                *context.get_global_property_value::<PropertyA>().unwrap()
+            );
             context.set_global_property_value::<PropertyB>(PropertyBValues {
-                old_a_value: *old_value.unwrap(),
+                old_a_value: *event.previous.unwrap(),
                 number_of_calls: number_of_calls + 1,
             });
         });
@@ -161,4 +576,226 @@ mod test {
         assert_eq!(current_b_value.number_of_calls, 2);
         assert_eq!(current_b_value.old_a_value, 2);
     }
+
+    define_global_property!(R0, f64);
+    define_global_property!(InfectiousPeriod, usize);
+    define_global_property!(Population, usize, 1000);
+
+    fn get_context() -> Context {
+        let mut context = Context::new();
+        context.register_global_property::<R0>("r0");
+        context.register_global_property::<InfectiousPeriod>("infectious_period");
+        context
+    }
+
+    #[test]
+    fn test_load_global_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("parameters.json");
+        std::fs::write(&path, r#"{"r0": 2.5, "infectious_period": 7}"#).unwrap();
+
+        let mut context = get_context();
+        context.load_global_properties(&path).unwrap();
+        assert_eq!(*context.get_global_property_value::<R0>().unwrap(), 2.5);
+        assert_eq!(
+            *context
+                .get_global_property_value::<InfectiousPeriod>()
+                .unwrap(),
+            7
+        );
+
+        // Properties that already have a value may be left out
+        let parameters = json!({"r0": 1.5});
+        context
+            .set_global_properties(parameters.as_object().unwrap())
+            .unwrap();
+        assert_eq!(*context.get_global_property_value::<R0>().unwrap(), 1.5);
+    }
+
+    #[test]
+    fn test_load_csv_global_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("parameters.csv");
+        std::fs::write(&path, "name,value\nr0,2.5\ninfectious_period,7\n").unwrap();
+
+        let mut context = get_context();
+        context.load_global_properties(&path).unwrap();
+        assert_eq!(*context.get_global_property_value::<R0>().unwrap(), 2.5);
+        assert_eq!(
+            *context
+                .get_global_property_value::<InfectiousPeriod>()
+                .unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_set_immutable_global_properties_after_start() {
+        let mut context = get_context();
+        context.make_global_property_immutable_after_start::<InfectiousPeriod>();
+        let parameters = json!({"r0": 2.5, "infectious_period": 7});
+        context
+            .set_global_properties(parameters.as_object().unwrap())
+            .unwrap();
+        context.execute();
+        let parameters = json!({"r0": 3.0, "infectious_period": 8});
+        match context.set_global_properties(parameters.as_object().unwrap()) {
+            Err(GlobalPropertyLoadError::ImmutableProperties(names)) => {
+                assert_eq!(names, vec!["infectious_period"])
+            }
+            _ => panic!("Expected immutable properties"),
+        }
+        // Nothing is set, including the mutable property
+        assert_eq!(*context.get_global_property_value::<R0>().unwrap(), 2.5);
+    }
+
+    #[test]
+    fn test_invalid_global_properties() {
+        let mut context = get_context();
+        let parameters = json!({"r0": 2.5, "beta": 0.1, "gamma": 0.2});
+        match context.set_global_properties(parameters.as_object().unwrap()) {
+            Err(GlobalPropertyLoadError::UnknownProperties(names)) => {
+                assert_eq!(names, vec!["beta", "gamma"])
+            }
+            _ => panic!("Expected unknown properties"),
+        }
+        let parameters = json!({"r0": 2.5});
+        match context.set_global_properties(parameters.as_object().unwrap()) {
+            Err(GlobalPropertyLoadError::MissingProperties(names)) => {
+                assert_eq!(names, vec!["infectious_period"])
+            }
+            _ => panic!("Expected missing properties"),
+        }
+        let parameters = json!({"r0": 2.5, "infectious_period": "seven"});
+        assert!(matches!(
+            context.set_global_properties(parameters.as_object().unwrap()),
+            Err(GlobalPropertyLoadError::InvalidValue(..))
+        ));
+        // Nothing is set when loading fails
+        assert!(context.get_global_property_value::<R0>().is_none());
+    }
+
+    #[test]
+    fn test_default_value() {
+        let mut context = Context::new();
+        assert_eq!(
+            *context.get_global_property_value_or_default::<Population>(),
+            1000
+        );
+        assert_eq!(
+            *context.get_global_property_value::<Population>().unwrap(),
+            1000
+        );
+        context.set_global_property_value::<Population>(10);
+        assert_eq!(
+            *context.get_global_property_value_or_default::<Population>(),
+            10
+        );
+
+        // Properties with a default don't need to be in parameter files
+        let mut context = get_context();
+        context.register_global_property::<Population>("population");
+        let parameters = json!({"r0": 2.5, "infectious_period": 7});
+        context
+            .set_global_properties(parameters.as_object().unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_required_global_properties() {
+        let mut context = Context::new();
+        assert!(context.validate_required_global_properties().is_ok());
+        context.require_global_property::<R0>();
+        context.require_global_property::<InfectiousPeriod>();
+        context.require_global_property::<Population>();
+        match context.validate_required_global_properties() {
+            Err(GlobalPropertyLoadError::MissingRequiredProperties(names)) => assert_eq!(
+                names,
+                vec![
+                    std::any::type_name::<InfectiousPeriod>(),
+                    std::any::type_name::<R0>()
+                ]
+            ),
+            _ => panic!("Expected missing properties"),
+        }
+        context.set_global_property_value::<R0>(2.5);
+        context.set_global_property_value::<InfectiousPeriod>(7);
+        assert!(context.validate_required_global_properties().is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing required global properties")]
+    fn test_required_global_property_on_start() {
+        let mut context = Context::new();
+        context.require_global_property::<R0>();
+        context.execute();
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing required global properties")]
+    fn test_required_global_property_after_start() {
+        let mut context = Context::new();
+        context.set_global_property_value::<R0>(2.5);
+        context.require_global_property::<R0>();
+        context.add_plan(1.0, |context| {
+            context.require_global_property::<InfectiousPeriod>();
+        });
+        context.execute();
+    }
+
+    #[test]
+    fn test_immutable_global_property() {
+        let mut context = Context::new();
+        context.make_global_property_immutable_after_start::<R0>();
+        context.set_global_property_value::<R0>(2.5);
+        context.add_before_start_callback(|context| {
+            // Values can still be set before the start
+            context.set_global_property_value::<R0>(3.0);
+        });
+        context.add_plan(1.0, |context| {
+            context.set_global_property_value::<InfectiousPeriod>(7);
+        });
+        context.execute();
+        assert_eq!(*context.get_global_property_value::<R0>().unwrap(), 3.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "R0 is immutable after the simulation starts")]
+    fn test_set_immutable_global_property_after_start() {
+        let mut context = Context::new();
+        context.make_global_property_immutable_after_start::<R0>();
+        context.add_plan(1.0, |context| {
+            context.set_global_property_value::<R0>(2.5);
+        });
+        context.execute();
+    }
+
+    #[test]
+    fn test_change_event() {
+        let mut context = Context::new();
+        context.observe_global_property_changes::<Population>(|context, event| {
+            let previous = *event.previous.unwrap();
+            let current = *event.current;
+            context.set_global_property_value::<PropertyA>(current - previous);
+        });
+        context.observe_global_property_changes::<R0>(|context, event| {
+            assert!(event.previous.is_none());
+            assert_eq!(*event.current, 2.5);
+            context.set_global_property_value::<InfectiousPeriod>(7);
+        });
+        // The previous value of a property with a default is the default
+        context.set_global_property_value::<Population>(1500);
+        context.set_global_property_value::<R0>(2.5);
+        context.execute();
+        assert_eq!(
+            *context.get_global_property_value::<PropertyA>().unwrap(),
+            500
+        );
+        assert_eq!(
+            *context
+                .get_global_property_value::<InfectiousPeriod>()
+                .unwrap(),
+            7
+        );
+    }
 }
@@ -1,7 +1,9 @@
+use crate::checkpoint::CheckpointContext;
 use crate::context::Context;
 use crate::data_containers::vector_person_container::VecPersonContainer;
 use crate::data_containers::{PersonContainer, PropertyWithDefault};
-use crate::people::PersonId;
+use crate::people::{check_person_not_removed, PersonId};
+use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::hash::Hash;
@@ -46,6 +48,8 @@ struct GroupsDataContainer {
     person_to_group_map: HashMap<TypeId, Vec<SetUsize>>,
     // Stores for each GroupType a vector by GroupId of the set of people by id in that group
     group_to_person_map: HashMap<TypeId, Vec<VecPersonContainer>>,
+    // Stores the type name of each GroupType with groups
+    group_type_names: HashMap<TypeId, &'static str>,
     // TODO: Group properties (by group type)
 }
 
@@ -56,9 +60,42 @@ crate::context::define_plugin!(
         max_group_id: HashMap::new(),
         person_to_group_map: HashMap::new(),
         group_to_person_map: HashMap::new(),
+        group_type_names: HashMap::new(),
     }
 );
 
+// Returns the checkpoint sections needed to save every group type
+pub(crate) fn get_required_checkpoint_sections(context: &Context) -> Vec<&'static str> {
+    match context.get_data_container::<GroupsPlugin>() {
+        None => Vec::new(),
+        Some(data_container) => data_container.group_type_names.values().copied().collect(),
+    }
+}
+
+// Drops the person from every group of every type
+pub(crate) fn remove_person_from_groups(context: &mut Context, person_id: PersonId) {
+    let Ok(data_container) = context.try_get_data_container_mut::<GroupsPlugin>() else {
+        return;
+    };
+    for (group_type_id, people_group_vec) in data_container.person_to_group_map.iter_mut() {
+        let Some(person_groups) = people_group_vec.get_mut(person_id.id) else {
+            continue;
+        };
+        if let Some(group_people_vec) = data_container.group_to_person_map.get_mut(group_type_id) {
+            for group_id in person_groups.iter() {
+                group_people_vec[group_id].remove(&person_id);
+            }
+        }
+        *person_groups = SetUsize::new();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GroupsCheckpointState {
+    max_group_id: Option<usize>,
+    group_members: Vec<Vec<PersonId>>,
+}
+
 pub trait GroupsContext {
     fn add_group<T: GroupType>(&mut self) -> GroupId<T>;
 
@@ -69,19 +106,25 @@ pub trait GroupsContext {
     fn get_group_members<T: GroupType>(&self, group_id: GroupId<T>) -> Option<&VecPersonContainer>;
 
     fn get_groups_for_person<T: GroupType>(&self, person_id: PersonId) -> Vec<GroupId<T>>;
+
+    // Saves the groups of type T and their members in checkpoints
+    fn register_checkpoint_group_type<T: GroupType>(&mut self);
 }
 
 impl GroupsContext for Context {
     fn add_group<T: GroupType>(&mut self) -> GroupId<T> {
-        let data_container = self.get_data_container_mut::<GroupsPlugin>();
+        let data_container = self.get_or_init_data_container::<GroupsPlugin>();
         let group_type_id = TypeId::of::<T>();
         let max_group_id = data_container.max_group_id.get_mut(&group_type_id);
-        return match max_group_id {
+        match max_group_id {
             None => {
                 let new_id = GroupId::<T>::new(0);
                 data_container
                     .max_group_id
                     .insert(group_type_id, Box::new(new_id));
+                data_container
+                    .group_type_names
+                    .insert(group_type_id, std::any::type_name::<T>());
                 new_id
             }
             Some(max_group_id) => {
@@ -89,22 +132,23 @@ impl GroupsContext for Context {
                 max_group_id.id += 1;
                 *max_group_id
             }
-        };
+        }
     }
 
     fn get_maximum_group_id<T: GroupType>(&self) -> Option<GroupId<T>> {
         let data_container = self.get_data_container::<GroupsPlugin>();
-        return match data_container {
+        match data_container {
             None => None,
             Some(data_container) => {
                 let max_group_id = data_container.max_group_id.get(&TypeId::of::<T>());
-                return max_group_id.map(|max_group_id| *max_group_id.downcast_ref().unwrap());
+                max_group_id.map(|max_group_id| *max_group_id.downcast_ref().unwrap())
             }
-        };
+        }
     }
 
     fn add_person_to_group<T: GroupType>(&mut self, person_id: PersonId, group_id: GroupId<T>) {
-        let data_container = self.get_data_container_mut::<GroupsPlugin>();
+        check_person_not_removed(self, person_id);
+        let data_container = self.get_or_init_data_container::<GroupsPlugin>();
         let group_type_id = TypeId::of::<T>();
         // Add person to group to person map
         let group_people_vec = data_container
@@ -158,11 +202,11 @@ impl GroupsContext for Context {
 
     fn get_groups_for_person<T: GroupType>(&self, person_id: PersonId) -> Vec<GroupId<T>> {
         let data_container = self.get_data_container::<GroupsPlugin>();
-        return match data_container {
+        match data_container {
             None => Vec::new(),
             Some(data_container) => {
                 let people_group_vec = data_container.person_to_group_map.get(&TypeId::of::<T>());
-                return match people_group_vec {
+                match people_group_vec {
                     None => Vec::new(),
                     Some(people_group_vec) => {
                         if person_id.id >= people_group_vec.len() {
@@ -173,11 +217,59 @@ impl GroupsContext for Context {
                         for group_id in group_ids.iter() {
                             group_id_vec.push(GroupId::new(group_id));
                         }
-                        return group_id_vec;
+                        group_id_vec
                     }
-                };
+                }
             }
-        };
+        }
+    }
+
+    fn register_checkpoint_group_type<T: GroupType>(&mut self) {
+        self.add_checkpoint_section::<GroupsCheckpointState>(
+            std::any::type_name::<T>(),
+            |context| {
+                let group_members = match context.get_data_container::<GroupsPlugin>() {
+                    None => Vec::new(),
+                    Some(data_container) => data_container
+                        .group_to_person_map
+                        .get(&TypeId::of::<T>())
+                        .map(|group_people_vec| {
+                            group_people_vec
+                                .iter()
+                                .map(|group_people| group_people.iter().collect())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                };
+                GroupsCheckpointState {
+                    max_group_id: context
+                        .get_maximum_group_id::<T>()
+                        .map(|group_id| group_id.id),
+                    group_members,
+                }
+            },
+            |context, state| {
+                let data_container = context.get_or_init_data_container::<GroupsPlugin>();
+                let group_type_id = TypeId::of::<T>();
+                data_container.max_group_id.remove(&group_type_id);
+                data_container.group_to_person_map.remove(&group_type_id);
+                data_container.person_to_group_map.remove(&group_type_id);
+                data_container.group_type_names.remove(&group_type_id);
+                if let Some(max_group_id) = state.max_group_id {
+                    data_container
+                        .max_group_id
+                        .insert(group_type_id, Box::new(GroupId::<T>::new(max_group_id)));
+                    data_container
+                        .group_type_names
+                        .insert(group_type_id, std::any::type_name::<T>());
+                }
+                for (group_id, group_members) in state.group_members.into_iter().enumerate() {
+                    for person_id in group_members {
+                        context.add_person_to_group(person_id, GroupId::<T>::new(group_id));
+                    }
+                }
+            },
+        );
     }
 }
 
@@ -186,7 +278,7 @@ mod test {
     use crate::context::Context;
     use crate::data_containers::PersonContainer;
     use crate::groups::{GroupType, GroupsContext};
-    use crate::people::PersonId;
+    use crate::people::{PeopleContext, PersonId};
 
     #[derive(Eq, PartialEq, Hash)]
     struct GroupTypeOne {}
@@ -272,4 +364,26 @@ mod test {
         let group_ids = context.get_groups_for_person::<GroupTypeTwo>(PersonId::new(3));
         assert_eq!(group_ids.len(), 0);
     }
+
+    #[test]
+    fn test_remove_person() {
+        let mut context = Context::new();
+        for _ in 0..3 {
+            context.add_person().execute().unwrap();
+        }
+        let group_one = context.add_group::<GroupTypeOne>();
+        let group_two = context.add_group::<GroupTypeTwo>();
+        context.add_person_to_group(PersonId::new(0), group_one);
+        context.add_person_to_group(PersonId::new(1), group_one);
+        context.add_person_to_group(PersonId::new(1), group_two);
+
+        context.remove_person(PersonId::new(1));
+        let members = context.get_group_members(group_one).unwrap();
+        assert_eq!(members.len(), 1);
+        assert!(members.contains(&PersonId::new(0)));
+        assert_eq!(context.get_group_members(group_two).unwrap().len(), 0);
+        assert!(context
+            .get_groups_for_person::<GroupTypeOne>(PersonId::new(1))
+            .is_empty());
+    }
 }
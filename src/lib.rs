@@ -1,11 +1,17 @@
+pub mod checkpoint;
 pub mod context;
-mod creation;
+pub mod creation;
 pub mod data_containers;
+pub mod events;
+pub mod experiment;
 pub mod global_properties;
 pub mod groups;
 pub mod partitions;
 pub mod people;
 pub mod person_properties;
+pub mod population;
+pub mod query;
 pub mod random;
 pub mod regions;
 pub mod reports;
+pub mod sweep;
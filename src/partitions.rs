@@ -1,23 +1,25 @@
 extern crate rand;
 
-use crate::context::Context;
+use crate::context::{Context, PluginError};
 use crate::data_containers::indexset_person_container::IndexSetPersonContainer;
 use crate::data_containers::PersonContainer;
-use crate::people::{PeopleContext, PersonId};
-use std::any::{Any, TypeId};
+use crate::people::{PeopleContext, PersonId, PersonIdRange};
+use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 
 type ContextCallback = dyn FnOnce(&mut Context);
 type LabelFunction<T> = dyn Fn(&Context, PersonId) -> T;
+pub type PartitionUpdateCallback = dyn Fn(&mut Context);
 pub type PartitionUpdateCallbackProvider =
-    dyn (Fn(&Context, PersonId) -> Box<dyn Fn(&mut Context)>);
+    dyn Fn(&Context, PersonId) -> Box<PartitionUpdateCallback>;
 
 pub trait Partition: Any {
     type LabelType: Any + Hash + Eq + Copy;
 
-    fn get_update_callback_provider() -> impl (Fn(&Context, PersonId) -> Box<dyn Fn(&mut Context)>)
+    fn get_update_callback_provider() -> impl Fn(&Context, PersonId) -> Box<PartitionUpdateCallback>
     where
         Self: Sized,
     {
@@ -44,6 +46,10 @@ struct PartitionData<T: Any + Hash + Eq> {
 struct PartitionDataContainer {
     // Will map TypeId::of<P: PartitionKey> to PartitionData<P::Value>
     partition_map: HashMap<TypeId, Box<dyn Any>>,
+    // Recomputes the labels of partition P for every person
+    rebuild_functions: HashMap<TypeId, fn(&mut Context)>,
+    // While true, people are not added to or moved between cells
+    updates_paused: bool,
 }
 
 crate::context::define_plugin!(
@@ -51,8 +57,90 @@ crate::context::define_plugin!(
     PartitionDataContainer,
     PartitionDataContainer {
         partition_map: HashMap::new(),
+        rebuild_functions: HashMap::new(),
+        updates_paused: false,
     }
 );
+
+// Rebuilds every partition from the current person data, for example after a
+// checkpoint has been loaded
+pub(crate) fn rebuild_partitions(context: &mut Context) {
+    let rebuild_functions: Vec<fn(&mut Context)> =
+        match context.get_data_container::<PartitionPlugin>() {
+            None => return,
+            Some(data_container) => data_container.rebuild_functions.values().copied().collect(),
+        };
+    for rebuild_function in rebuild_functions {
+        rebuild_function(context);
+    }
+}
+
+// Stops partitions from tracking people until the returned guard is
+// dropped, which is faster than updating them one person at a time when
+// adding many people at once. Dropping the guard rebuilds the partitions,
+// so they are brought up to date even if adding people fails or panics.
+pub(crate) fn pause_partition_updates(context: &mut Context) -> PausedPartitionUpdates<'_> {
+    if let Ok(data_container) = context.try_get_data_container_mut::<PartitionPlugin>() {
+        data_container.updates_paused = true;
+    }
+    PausedPartitionUpdates { context }
+}
+
+pub(crate) struct PausedPartitionUpdates<'a> {
+    context: &'a mut Context,
+}
+
+impl Deref for PausedPartitionUpdates<'_> {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        self.context
+    }
+}
+
+impl DerefMut for PausedPartitionUpdates<'_> {
+    fn deref_mut(&mut self) -> &mut Context {
+        self.context
+    }
+}
+
+impl Drop for PausedPartitionUpdates<'_> {
+    fn drop(&mut self) {
+        if let Ok(data_container) = self.context.try_get_data_container_mut::<PartitionPlugin>() {
+            data_container.updates_paused = false;
+            rebuild_partitions(self.context);
+        }
+    }
+}
+
+fn are_partition_updates_paused(context: &Context) -> bool {
+    context
+        .get_data_container::<PartitionPlugin>()
+        .is_some_and(|data_container| data_container.updates_paused)
+}
+
+fn build_label_map<T: Any + Hash + Eq + Copy>(
+    context: &Context,
+    label_function: &LabelFunction<T>,
+) -> HashMap<T, IndexSetPersonContainer> {
+    // Count the people with each label first so every cell is allocated once
+    let labels: Vec<(T, PersonId)> = context
+        .iter_people()
+        .map(|person_id| (label_function(context, person_id), person_id))
+        .collect();
+    let mut label_counts: HashMap<T, usize> = HashMap::new();
+    for (label, _) in &labels {
+        *label_counts.entry(*label).or_default() += 1;
+    }
+    let mut label_map: HashMap<T, IndexSetPersonContainer> = label_counts
+        .into_iter()
+        .map(|(label, count)| (label, IndexSetPersonContainer::with_capacity(count)))
+        .collect();
+    for (label, person_id) in labels {
+        label_map.get_mut(&label).unwrap().insert(person_id);
+    }
+    label_map
+}
 pub struct PartitionBuilder<'a, P: Partition> {
     context: &'a mut Context,
     label_function: Option<Rc<LabelFunction<P::LabelType>>>,
@@ -71,6 +159,12 @@ impl<'a, P: Partition> PartitionBuilder<'a, P> {
                     context.add_immediate_creation_callback::<P>(|context, person_id| {
                         context.handle_person_creation::<P>(person_id)
                     });
+                    context.add_immediate_batch_creation_callback::<P>(|context, range| {
+                        context.handle_people_batch_creation::<P>(range)
+                    });
+                    context.add_immediate_removal_callback::<P>(|context, person_id| {
+                        context.handle_person_removal::<P>(person_id)
+                    });
                 }));
                 registration_callbacks
             },
@@ -78,6 +172,8 @@ impl<'a, P: Partition> PartitionBuilder<'a, P> {
                 let mut deregistration_callbacks: Vec<Box<ContextCallback>> = Vec::new();
                 deregistration_callbacks.push(Box::new(|context: &mut Context| {
                     context.remove_immediate_creation_callback::<P>();
+                    context.remove_immediate_batch_creation_callback::<P>();
+                    context.remove_immediate_removal_callback::<P>();
                 }));
                 deregistration_callbacks
             },
@@ -125,23 +221,47 @@ impl<'a, P: Partition> PartitionBuilder<'a, P> {
 }
 
 pub trait PartitionContext {
-    fn add_partition<P: Partition>(&mut self) -> PartitionBuilder<P>;
+    fn add_partition<P: Partition>(&mut self) -> PartitionBuilder<'_, P>;
     fn remove_partition<P: Partition>(&mut self);
     fn get_partition_label<P: Partition>(&self, person_id: PersonId) -> P::LabelType;
     fn get_partition_cell<P: Partition>(
         &self,
         label: P::LabelType,
     ) -> Option<&IndexSetPersonContainer>;
+
+    // Return an error instead of panicking if P hasn't been added
+    fn try_get_partition_label<P: Partition>(
+        &self,
+        person_id: PersonId,
+    ) -> Result<P::LabelType, PluginError>;
+    fn try_get_partition_cell<P: Partition>(
+        &self,
+        label: P::LabelType,
+    ) -> Result<Option<&IndexSetPersonContainer>, PluginError>;
+}
+
+fn get_partition_data<P: Partition>(
+    context: &Context,
+) -> Result<&PartitionData<P::LabelType>, PluginError> {
+    let partition_data = context
+        .try_get_data_container::<PartitionPlugin>()?
+        .partition_map
+        .get(&TypeId::of::<P>())
+        .ok_or(PluginError::NotAdded(type_name::<P>()))?;
+    Ok(partition_data
+        .downcast_ref::<PartitionData<P::LabelType>>()
+        .unwrap())
 }
 
 impl PartitionContext for Context {
-    fn add_partition<P: Partition>(&mut self) -> PartitionBuilder<P> {
+    fn add_partition<P: Partition>(&mut self) -> PartitionBuilder<'_, P> {
         PartitionBuilder::new(self)
     }
 
     fn remove_partition<P: Partition>(&mut self) {
-        let data_container = self.get_data_container_mut::<PartitionPlugin>();
+        let data_container = self.get_or_init_data_container::<PartitionPlugin>();
         let partition_data = data_container.partition_map.remove(&TypeId::of::<P>());
+        data_container.rebuild_functions.remove(&TypeId::of::<P>());
         match partition_data {
             None => {
                 panic!("Partition does not exist")
@@ -156,33 +276,31 @@ impl PartitionContext for Context {
     }
 
     fn get_partition_label<P: Partition>(&self, person_id: PersonId) -> P::LabelType {
-        let data_container = self.get_data_container::<PartitionPlugin>().unwrap();
-        let partition_data = data_container.partition_map.get(&TypeId::of::<P>());
-        match partition_data {
-            None => panic!("Partition not registered in Context"),
-            Some(partition_data) => {
-                let partition_data = partition_data
-                    .downcast_ref::<PartitionData<P::LabelType>>()
-                    .unwrap();
-                (*partition_data.label_function)(self, person_id)
-            }
-        }
+        self.try_get_partition_label::<P>(person_id)
+            .unwrap_or_else(|e| panic!("{}", e))
     }
 
     fn get_partition_cell<P: Partition>(
         &self,
         label: P::LabelType,
     ) -> Option<&IndexSetPersonContainer> {
-        let data_container = self
-            .get_data_container::<PartitionPlugin>()
-            .expect("Partition plugin not loaded");
-        let data = data_container
-            .partition_map
-            .get(&TypeId::of::<P>())
-            .expect("Partition with specified key not loaded")
-            .downcast_ref::<PartitionData<P::LabelType>>()
-            .unwrap();
-        data.label_map.get(&label)
+        self.try_get_partition_cell::<P>(label)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_get_partition_label<P: Partition>(
+        &self,
+        person_id: PersonId,
+    ) -> Result<P::LabelType, PluginError> {
+        let partition_data = get_partition_data::<P>(self)?;
+        Ok((*partition_data.label_function)(self, person_id))
+    }
+
+    fn try_get_partition_cell<P: Partition>(
+        &self,
+        label: P::LabelType,
+    ) -> Result<Option<&IndexSetPersonContainer>, PluginError> {
+        Ok(get_partition_data::<P>(self)?.label_map.get(&label))
     }
 }
 
@@ -192,9 +310,15 @@ trait InternalPartitionContext {
         specification: PartitionSpecification<P::LabelType>,
     );
 
+    fn rebuild_partition<P: Partition>(&mut self);
+
     fn reevaluate_person<P: Partition>(&mut self, person_id: PersonId, old_label: P::LabelType);
 
     fn handle_person_creation<P: Partition>(&mut self, person_id: PersonId);
+
+    fn handle_people_batch_creation<P: Partition>(&mut self, range: PersonIdRange);
+
+    fn handle_person_removal<P: Partition>(&mut self, person_id: PersonId);
 }
 
 impl InternalPartitionContext for Context {
@@ -203,25 +327,13 @@ impl InternalPartitionContext for Context {
         specification: PartitionSpecification<P::LabelType>,
     ) {
         // First build up the map of labels to PersonContainers
-        let mut label_map: HashMap<P::LabelType, IndexSetPersonContainer> = HashMap::new();
-        let maximum_person_id = self.get_maximum_person_id();
-        if maximum_person_id.is_some() {
-            // If there are people in the simulation, add them to the partition
-            for i in 0..(maximum_person_id.unwrap().id + 1) {
-                let person_id = PersonId::new(i);
-                let label = (specification.label_function)(self, person_id);
-                label_map
-                    .entry(label)
-                    .or_insert(IndexSetPersonContainer::new())
-                    .insert(person_id)
-            }
-        }
+        let label_map = build_label_map(self, &*specification.label_function);
 
         // Register for updates
         (specification.registration_callback)(self);
 
         // Store data
-        let data_container = self.get_data_container_mut::<PartitionPlugin>();
+        let data_container = self.get_or_init_data_container::<PartitionPlugin>();
         let partition_data = PartitionData {
             label_map,
             label_function: specification.label_function,
@@ -230,10 +342,44 @@ impl InternalPartitionContext for Context {
         data_container
             .partition_map
             .insert(TypeId::of::<P>(), Box::new(partition_data));
+        data_container
+            .rebuild_functions
+            .insert(TypeId::of::<P>(), |context| {
+                context.rebuild_partition::<P>()
+            });
+    }
+
+    fn rebuild_partition<P: Partition>(&mut self) {
+        let data_container = self
+            .try_get_data_container::<PartitionPlugin>()
+            .unwrap_or_else(|e| panic!("{}", e));
+        let label_function = Rc::clone(
+            &data_container
+                .partition_map
+                .get(&TypeId::of::<P>())
+                .expect("Partition not registered in Context")
+                .downcast_ref::<PartitionData<P::LabelType>>()
+                .unwrap()
+                .label_function,
+        );
+        let label_map = build_label_map(self, &*label_function);
+        let data_container = self.get_or_init_data_container::<PartitionPlugin>();
+        let partition_data = data_container
+            .partition_map
+            .get_mut(&TypeId::of::<P>())
+            .unwrap()
+            .downcast_mut::<PartitionData<P::LabelType>>()
+            .unwrap();
+        partition_data.label_map = label_map;
     }
 
     fn reevaluate_person<P: Partition>(&mut self, person_id: PersonId, old_label: P::LabelType) {
-        let data_container = self.get_data_container::<PartitionPlugin>().unwrap();
+        if are_partition_updates_paused(self) {
+            return;
+        }
+        let data_container = self
+            .try_get_data_container::<PartitionPlugin>()
+            .unwrap_or_else(|e| panic!("{}", e));
         let partition_data = data_container.partition_map.get(&TypeId::of::<P>());
         if partition_data.is_none() {
             panic!("Partition not registered in Context");
@@ -244,7 +390,7 @@ impl InternalPartitionContext for Context {
             .expect("Partition data of wrong type");
         let new_label = (*partition_data.label_function)(self, person_id);
 
-        let data_container = self.get_data_container_mut::<PartitionPlugin>();
+        let data_container = self.get_or_init_data_container::<PartitionPlugin>();
         let partition_data = data_container.partition_map.get_mut(&TypeId::of::<P>());
         match partition_data {
             None => panic!("Unreachable"),
@@ -273,7 +419,12 @@ impl InternalPartitionContext for Context {
     }
 
     fn handle_person_creation<P: Partition>(&mut self, person_id: PersonId) {
-        let data_container = self.get_data_container::<PartitionPlugin>().unwrap();
+        if are_partition_updates_paused(self) {
+            return;
+        }
+        let data_container = self
+            .try_get_data_container::<PartitionPlugin>()
+            .unwrap_or_else(|e| panic!("{}", e));
         let partition_data = data_container.partition_map.get(&TypeId::of::<P>());
         if partition_data.is_none() {
             panic!("Partition not registered in Context");
@@ -284,7 +435,7 @@ impl InternalPartitionContext for Context {
             .expect("Partition data of wrong type");
         let label = (*partition_data.label_function)(self, person_id);
 
-        let data_container = self.get_data_container_mut::<PartitionPlugin>();
+        let data_container = self.get_or_init_data_container::<PartitionPlugin>();
         let partition_data = data_container.partition_map.get_mut(&TypeId::of::<P>());
         match partition_data {
             None => panic!("Unreachable"),
@@ -300,16 +451,89 @@ impl InternalPartitionContext for Context {
             }
         }
     }
+
+    fn handle_people_batch_creation<P: Partition>(&mut self, range: PersonIdRange) {
+        if are_partition_updates_paused(self) {
+            return;
+        }
+        let data_container = self
+            .try_get_data_container::<PartitionPlugin>()
+            .unwrap_or_else(|e| panic!("{}", e));
+        let label_function = Rc::clone(
+            &data_container
+                .partition_map
+                .get(&TypeId::of::<P>())
+                .expect("Partition not registered in Context")
+                .downcast_ref::<PartitionData<P::LabelType>>()
+                .unwrap()
+                .label_function,
+        );
+        let labels: Vec<P::LabelType> = range
+            .iter()
+            .map(|person_id| label_function(self, person_id))
+            .collect();
+        let mut label_counts: HashMap<P::LabelType, usize> = HashMap::new();
+        for label in &labels {
+            *label_counts.entry(*label).or_default() += 1;
+        }
+
+        let data_container = self.get_or_init_data_container::<PartitionPlugin>();
+        let partition_data = data_container
+            .partition_map
+            .get_mut(&TypeId::of::<P>())
+            .unwrap()
+            .downcast_mut::<PartitionData<P::LabelType>>()
+            .unwrap();
+        for (label, count) in label_counts {
+            partition_data
+                .label_map
+                .entry(label)
+                .or_default()
+                .reserve(count);
+        }
+        for (label, person_id) in labels.into_iter().zip(range.iter()) {
+            partition_data
+                .label_map
+                .get_mut(&label)
+                .unwrap()
+                .insert(person_id);
+        }
+    }
+
+    fn handle_person_removal<P: Partition>(&mut self, person_id: PersonId) {
+        if are_partition_updates_paused(self) {
+            return;
+        }
+        let data_container = self
+            .try_get_data_container_mut::<PartitionPlugin>()
+            .unwrap_or_else(|e| panic!("{}", e));
+        let partition_data = data_container
+            .partition_map
+            .get_mut(&TypeId::of::<P>())
+            .expect("Partition not registered in Context")
+            .downcast_mut::<PartitionData<P::LabelType>>()
+            .unwrap();
+        // Look for the person in every cell rather than computing their
+        // label, since the label function may not accept removed people
+        for label_people_container in partition_data.label_map.values_mut() {
+            if label_people_container.contains(&person_id) {
+                label_people_container.remove(&person_id);
+                break;
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::context::Context;
+    use crate::context::{Context, PluginError};
     use crate::data_containers::PersonContainer;
     use crate::define_person_property;
     use crate::partitions::{Partition, PartitionContext};
     use crate::people::{PeopleContext, PersonId};
-    use crate::person_properties::{PersonPropertyContext, PersonPropertyPartitionBuilder};
+    use crate::person_properties::{
+        PersonPropertiesPeopleBatchBuilder, PersonPropertyContext, PersonPropertyPartitionBuilder,
+    };
     use rand::prelude::StdRng;
     use rand::{Rng, SeedableRng};
     use std::collections::HashSet;
@@ -343,7 +567,7 @@ mod test {
             .execute();
 
         for _ in 0..population {
-            context.add_person().execute();
+            context.add_person().execute().unwrap();
         }
 
         let zero_false_value_people = context
@@ -395,4 +619,126 @@ mod test {
 
         context.remove_partition::<PartitionOne>();
     }
+
+    #[test]
+    fn test_try_get_partition() {
+        let mut context = Context::new();
+        assert_eq!(
+            context.try_get_partition_label::<PartitionOne>(PersonId::new(0)),
+            Err(PluginError::NotRegistered(std::any::type_name::<
+                super::PartitionPlugin,
+            >()))
+        );
+        context
+            .add_partition::<PartitionOne>()
+            .set_label_function(|context, person_id| {
+                (
+                    context.get_person_property_value::<PropertyOne>(person_id),
+                    false,
+                )
+            })
+            .add_person_property_sensitivity::<PropertyOne>()
+            .execute();
+        context.remove_partition::<PartitionOne>();
+        assert_eq!(
+            context
+                .try_get_partition_cell::<PartitionOne>((0, false))
+                .err(),
+            Some(PluginError::NotAdded(std::any::type_name::<PartitionOne>()))
+        );
+    }
+
+    #[test]
+    fn test_remove_person() {
+        let mut context = Context::new();
+        for _ in 0..3 {
+            context.add_person().execute().unwrap();
+        }
+        context.set_person_property_value::<PropertyOne>(PersonId::new(2), 1);
+        context.remove_person(PersonId::new(0));
+
+        context
+            .add_partition::<PartitionOne>()
+            .set_label_function(|context, person_id| {
+                (
+                    context.get_person_property_value::<PropertyOne>(person_id),
+                    context.get_person_property_value::<PropertyTwo>(person_id),
+                )
+            })
+            .add_person_property_sensitivity::<PropertyOne>()
+            .add_person_property_sensitivity::<PropertyTwo>()
+            .execute();
+        // Removed people are left out when the partition is built
+        let zero_false_value_people = context
+            .get_partition_cell::<PartitionOne>((0, false))
+            .unwrap();
+        assert_eq!(zero_false_value_people.len(), 1);
+
+        context.set_person_property_value::<PropertyOne>(PersonId::new(1), 1);
+        context.remove_person(PersonId::new(2));
+        let one_false_value_people = context
+            .get_partition_cell::<PartitionOne>((1, false))
+            .unwrap();
+        assert_eq!(one_false_value_people.len(), 1);
+        assert!(one_false_value_people.contains(&PersonId::new(1)));
+    }
+
+    #[test]
+    fn test_add_people() {
+        let mut context = Context::new();
+        context.add_person().execute().unwrap();
+        context
+            .add_partition::<PartitionOne>()
+            .set_label_function(|context, person_id| {
+                (
+                    context.get_person_property_value::<PropertyOne>(person_id),
+                    context.get_person_property_value::<PropertyTwo>(person_id),
+                )
+            })
+            .add_person_property_sensitivity::<PropertyOne>()
+            .add_person_property_sensitivity::<PropertyTwo>()
+            .execute();
+
+        let range = context
+            .add_people(4)
+            .set_person_property_values::<PropertyOne>(vec![1, 1, 2, 0])
+            .set_person_property_values::<PropertyTwo>(vec![true, true, false, false])
+            .execute()
+            .unwrap();
+        assert_eq!(
+            context.get_person_property_value::<PropertyOne>(range.start),
+            1
+        );
+        let one_true_people = context
+            .get_partition_cell::<PartitionOne>((1, true))
+            .unwrap();
+        assert_eq!(one_true_people.len(), 2);
+        assert!(one_true_people.contains(&PersonId::new(2)));
+        assert_eq!(
+            context
+                .get_partition_cell::<PartitionOne>((0, false))
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // People added in a batch are updated like any other
+        context.set_person_property_value::<PropertyTwo>(PersonId::new(3), true);
+        assert_eq!(
+            context
+                .get_partition_cell::<PartitionOne>((2, true))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected 2 values")]
+    fn test_add_people_wrong_length() {
+        let mut context = Context::new();
+        context
+            .add_people(2)
+            .set_person_property_values::<PropertyOne>(vec![1]);
+    }
 }
@@ -1,11 +1,14 @@
+use crate::checkpoint::{CheckpointPlugin, CheckpointSection};
 use crate::context::Context;
-use crate::creation::CreationBuilder;
+use crate::creation::{check_required_initializers, CreationBuilder, CreationError};
+use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::rc::Rc;
 
-#[derive(Hash, Eq, PartialEq, Clone, Copy, Ord, PartialOrd, Debug)]
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Ord, PartialOrd, Debug, Serialize, Deserialize)]
 pub struct PersonId {
     pub id: usize,
 }
@@ -18,9 +21,99 @@ impl PersonId {
 
 pub type PersonBuilder<'a> = CreationBuilder<'a, PersonId>;
 
+// A run of consecutive people, such as those added in one batch. The
+// range includes start but not end.
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct PersonIdRange {
+    pub start: PersonId,
+    pub end: PersonId,
+}
+
+impl PersonIdRange {
+    pub fn new(start: usize, end: usize) -> PersonIdRange {
+        PersonIdRange {
+            start: PersonId::new(start),
+            end: PersonId::new(end),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.id - self.start.id
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, person_id: PersonId) -> bool {
+        self.start <= person_id && person_id < self.end
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PersonId> {
+        (self.start.id..self.end.id).map(PersonId::new)
+    }
+}
+
+type PeopleBatchInitializer = dyn FnOnce(&mut Context, PersonIdRange);
+
+// Adds many people at once. Initial values are given per batch, e.g., one
+// vector of values for each person property, rather than per person.
+pub struct PeopleBatchBuilder<'a> {
+    context: &'a mut Context,
+    count: usize,
+    initializers: Vec<Box<PeopleBatchInitializer>>,
+    initialized: HashSet<TypeId>,
+}
+
+impl<'a> PeopleBatchBuilder<'a> {
+    fn new(context: &'a mut Context, count: usize) -> PeopleBatchBuilder<'a> {
+        PeopleBatchBuilder {
+            context,
+            count,
+            initializers: Vec::new(),
+            initialized: HashSet::new(),
+        }
+    }
+
+    // The number of people in the batch
+    pub fn get_count(&self) -> usize {
+        self.count
+    }
+
+    // Adds a callback which satisfies the initializer requirement keyed by K
+    pub fn add_initializer<K: Any>(
+        &mut self,
+        callback: impl FnOnce(&mut Context, PersonIdRange) + 'static,
+    ) {
+        self.initialized.insert(TypeId::of::<K>());
+        self.initializers.push(Box::new(callback));
+    }
+
+    pub fn execute(self) -> Result<PersonIdRange, CreationError> {
+        check_required_initializers::<PersonId>(self.context, &self.initialized)?;
+
+        let data_container = self.context.get_or_init_data_container::<PeoplePlugin>();
+        let start = data_container
+            .max_person_id
+            .map_or(0, |max_person_id| max_person_id.id + 1);
+        let range = PersonIdRange::new(start, start + self.count);
+        if range.is_empty() {
+            return Ok(range);
+        }
+        data_container.max_person_id = Some(PersonId::new(range.end.id - 1));
+
+        for initializer in self.initializers {
+            (initializer)(self.context, range);
+        }
+
+        finalize_people_batch_creation(self.context, range);
+        Ok(range)
+    }
+}
+
 fn create_person(context: &mut Context) -> PersonId {
     // Add a person to the simulation
-    let data_container = context.get_data_container_mut::<PeoplePlugin>();
+    let data_container = context.get_or_init_data_container::<PeoplePlugin>();
     let person_id = match data_container.max_person_id {
         None => PersonId::new(0),
         Some(max_person_id) => PersonId::new(max_person_id.id + 1),
@@ -29,30 +122,71 @@ fn create_person(context: &mut Context) -> PersonId {
     person_id
 }
 
+// A person added on their own is finalized as a batch of one, so the
+// same callbacks run in the same order whichever way people are added
 fn finalize_person_creation(context: &mut Context, person_id: PersonId) {
-    let data_container = context.get_data_container::<PeoplePlugin>().unwrap();
-    // Collect the immediate execution callbacks
+    finalize_people_batch_creation(context, PersonIdRange::new(person_id.id, person_id.id + 1));
+}
+
+fn finalize_people_batch_creation(context: &mut Context, range: PersonIdRange) {
+    let data_container = context
+        .try_get_data_container::<PeoplePlugin>()
+        .unwrap_or_else(|e| panic!("{}", e));
     let immediate_callbacks = Rc::clone(&data_container.creation_immediate_callbacks);
-    // Collect the observation callbacks
     let creation_observers = Rc::clone(&data_container.creation_observers);
+    let batch_immediate_callbacks = Rc::clone(&data_container.batch_creation_immediate_callbacks);
+    let batch_creation_observers = Rc::clone(&data_container.batch_creation_observers);
 
-    // Perform the immediate execution callbacks
+    crate::person_properties::initialize_person_properties(context, range);
+
+    // Perform the immediate execution callbacks, per person callbacks
+    // first
     for callback in immediate_callbacks.borrow().values() {
-        (callback)(context, person_id);
+        for person_id in range.iter() {
+            (callback)(context, person_id);
+        }
+    }
+    for callback in batch_immediate_callbacks.borrow().values() {
+        (callback)(context, range);
     }
 
-    // Add the observation callbacks
+    // Add the observation callbacks, each of which is queued once for the
+    // whole batch
     for callback in creation_observers.borrow().values() {
         let internal_callback = Rc::clone(callback);
-        context.queue_callback(move |context| (internal_callback)(context, person_id));
+        context.queue_callback(move |context| {
+            for person_id in range.iter() {
+                (internal_callback)(context, person_id);
+            }
+        });
+    }
+    for callback in batch_creation_observers.borrow().values() {
+        let internal_callback = Rc::clone(callback);
+        context.queue_callback(move |context| (internal_callback)(context, range));
     }
 }
 
 type PersonCreationCallback = dyn Fn(&mut Context, PersonId);
+type PersonRemovalCallback = dyn Fn(&mut Context, PersonId);
+type PeopleBatchCreationCallback = dyn Fn(&mut Context, PersonIdRange);
 struct PeopleDataContainer {
     max_person_id: Option<PersonId>,
+    // Indexed by PersonId; only grows once someone is removed
+    removed: Vec<bool>,
+    removed_count: usize,
     creation_immediate_callbacks: Rc<RefCell<HashMap<TypeId, Rc<PersonCreationCallback>>>>,
     creation_observers: Rc<RefCell<HashMap<TypeId, Rc<PersonCreationCallback>>>>,
+    batch_creation_immediate_callbacks:
+        Rc<RefCell<HashMap<TypeId, Rc<PeopleBatchCreationCallback>>>>,
+    batch_creation_observers: Rc<RefCell<HashMap<TypeId, Rc<PeopleBatchCreationCallback>>>>,
+    removal_immediate_callbacks: Rc<RefCell<HashMap<TypeId, Rc<PersonRemovalCallback>>>>,
+    removal_observers: Rc<RefCell<HashMap<TypeId, Rc<PersonRemovalCallback>>>>,
+}
+
+impl PeopleDataContainer {
+    fn is_removed(&self, person_id: PersonId) -> bool {
+        self.removed.get(person_id.id).copied().unwrap_or(false)
+    }
 }
 
 crate::context::define_plugin!(
@@ -60,16 +194,109 @@ crate::context::define_plugin!(
     PeopleDataContainer,
     PeopleDataContainer {
         max_person_id: None,
+        removed: Vec::new(),
+        removed_count: 0,
         creation_immediate_callbacks: Rc::new(RefCell::new(HashMap::new())),
         creation_observers: Rc::new(RefCell::new(HashMap::new())),
+        batch_creation_immediate_callbacks: Rc::new(RefCell::new(HashMap::new())),
+        batch_creation_observers: Rc::new(RefCell::new(HashMap::new())),
+        removal_immediate_callbacks: Rc::new(RefCell::new(HashMap::new())),
+        removal_observers: Rc::new(RefCell::new(HashMap::new())),
     }
 );
 
+#[derive(Debug, PartialEq)]
+pub struct RemovedPersonError {
+    pub person_id: PersonId,
+}
+
+impl fmt::Display for RemovedPersonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Person {} has been removed", self.person_id.id)
+    }
+}
+
+impl std::error::Error for RemovedPersonError {}
+
+pub(crate) fn try_check_person_not_removed(
+    context: &Context,
+    person_id: PersonId,
+) -> Result<(), RemovedPersonError> {
+    match context.get_data_container::<PeoplePlugin>() {
+        Some(data_container) if data_container.is_removed(person_id) => {
+            Err(RemovedPersonError { person_id })
+        }
+        _ => Ok(()),
+    }
+}
+
+// Panics if the person has been removed from the simulation
+pub(crate) fn check_person_not_removed(context: &Context, person_id: PersonId) {
+    if let Err(e) = try_check_person_not_removed(context, person_id) {
+        panic!("{}", e);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PeopleCheckpointState {
+    max_person_id: Option<PersonId>,
+    removed_people: Vec<PersonId>,
+}
+
+impl CheckpointPlugin for PeoplePlugin {
+    type State = PeopleCheckpointState;
+
+    fn save_state(data_container: &PeopleDataContainer) -> Self::State {
+        PeopleCheckpointState {
+            max_person_id: data_container.max_person_id,
+            removed_people: (0..data_container.removed.len())
+                .map(PersonId::new)
+                .filter(|person_id| data_container.is_removed(*person_id))
+                .collect(),
+        }
+    }
+
+    fn load_state(data_container: &mut PeopleDataContainer, state: Self::State) {
+        data_container.max_person_id = state.max_person_id;
+        data_container.removed.clear();
+        data_container.removed_count = state.removed_people.len();
+        for person_id in state.removed_people {
+            if person_id.id >= data_container.removed.len() {
+                data_container.removed.resize(person_id.id + 1, false);
+            }
+            data_container.removed[person_id.id] = true;
+        }
+    }
+}
+
+pub(crate) fn get_checkpoint_section() -> CheckpointSection {
+    CheckpointSection::for_plugin::<PeoplePlugin>()
+}
+
 pub trait PeopleContext {
-    fn add_person(&mut self) -> PersonBuilder;
+    fn add_person(&mut self) -> PersonBuilder<'_>;
+
+    // Adds count people with consecutive ids
+    fn add_people(&mut self, count: usize) -> PeopleBatchBuilder<'_>;
 
     fn get_maximum_person_id(&self) -> Option<PersonId>;
 
+    // Returns the number of people who have been added and not removed
+    fn get_population(&self) -> usize;
+
+    // Reading or setting a removed person's properties panics, so code
+    // which may hold removed ids should check this first or use
+    // try_get_person_property_value()
+    fn is_person_alive(&self, person_id: PersonId) -> bool;
+
+    // Iterates over the people who haven't been removed, in id order
+    fn iter_people(&self) -> impl Iterator<Item = PersonId> + '_;
+
+    // Removes a person from the simulation, e.g., on death or emigration.
+    // The person is dropped from partitions and groups and their
+    // properties can no longer be read or set.
+    fn remove_person(&mut self, person_id: PersonId);
+
     fn observe_person_creation<T: Any>(
         &mut self,
         callback: impl Fn(&mut Context, PersonId) + 'static,
@@ -83,13 +310,52 @@ pub trait PeopleContext {
     );
 
     fn remove_immediate_creation_callback<T: Any>(&mut self);
+
+    // Observes people created in batches with one call per batch. People
+    // added one at a time are passed as a range of one.
+    fn observe_people_batch_creation<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonIdRange) + 'static,
+    );
+
+    fn ignore_people_batch_creation<T: Any>(&mut self);
+
+    // Adds a callback which runs once for each batch of people, after the
+    // immediate creation callbacks. It is kept apart from the immediate
+    // creation callback with the same key, so both run. People added one at
+    // a time are passed as a range of one.
+    fn add_immediate_batch_creation_callback<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonIdRange) + 'static,
+    );
+
+    fn remove_immediate_batch_creation_callback<T: Any>(&mut self);
+
+    fn observe_person_removal<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonId) + 'static,
+    );
+
+    fn ignore_person_removal<T: Any>(&mut self);
+
+    // Adds a callback which runs before the person is removed
+    fn add_immediate_removal_callback<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonId) + 'static,
+    );
+
+    fn remove_immediate_removal_callback<T: Any>(&mut self);
 }
 
 impl PeopleContext for Context {
-    fn add_person(&mut self) -> PersonBuilder {
+    fn add_person(&mut self) -> PersonBuilder<'_> {
         PersonBuilder::new(self, create_person, finalize_person_creation)
     }
 
+    fn add_people(&mut self, count: usize) -> PeopleBatchBuilder<'_> {
+        PeopleBatchBuilder::new(self, count)
+    }
+
     fn get_maximum_person_id(&self) -> Option<PersonId> {
         let data_container = self.get_data_container::<PeoplePlugin>();
         match data_container {
@@ -98,11 +364,77 @@ impl PeopleContext for Context {
         }
     }
 
+    fn get_population(&self) -> usize {
+        match self.get_data_container::<PeoplePlugin>() {
+            None => 0,
+            Some(data_container) => match data_container.max_person_id {
+                None => 0,
+                Some(max_person_id) => max_person_id.id + 1 - data_container.removed_count,
+            },
+        }
+    }
+
+    fn is_person_alive(&self, person_id: PersonId) -> bool {
+        match self.get_data_container::<PeoplePlugin>() {
+            None => false,
+            Some(data_container) => {
+                data_container
+                    .max_person_id
+                    .is_some_and(|max_person_id| person_id.id <= max_person_id.id)
+                    && !data_container.is_removed(person_id)
+            }
+        }
+    }
+
+    fn iter_people(&self) -> impl Iterator<Item = PersonId> + '_ {
+        let data_container = self.get_data_container::<PeoplePlugin>();
+        let population_size = data_container
+            .and_then(|data_container| data_container.max_person_id)
+            .map_or(0, |max_person_id| max_person_id.id + 1);
+        (0..population_size)
+            .map(PersonId::new)
+            .filter(move |person_id| {
+                data_container.is_none_or(|data_container| !data_container.is_removed(*person_id))
+            })
+    }
+
+    fn remove_person(&mut self, person_id: PersonId) {
+        if !self.is_person_alive(person_id) {
+            panic!(
+                "Person {} does not exist or was already removed",
+                person_id.id
+            );
+        }
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        let immediate_callbacks = Rc::clone(&data_container.removal_immediate_callbacks);
+        let removal_observers = Rc::clone(&data_container.removal_observers);
+
+        // Perform the immediate callbacks while the person still exists
+        for callback in immediate_callbacks.borrow().values() {
+            (callback)(self, person_id);
+        }
+        crate::groups::remove_person_from_groups(self, person_id);
+        crate::regions::remove_person_from_region(self, person_id);
+
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        if person_id.id >= data_container.removed.len() {
+            data_container.removed.resize(person_id.id + 1, false);
+        }
+        data_container.removed[person_id.id] = true;
+        data_container.removed_count += 1;
+
+        // Add the observation callbacks
+        for callback in removal_observers.borrow().values() {
+            let internal_callback = Rc::clone(callback);
+            self.queue_callback(move |context| (internal_callback)(context, person_id));
+        }
+    }
+
     fn observe_person_creation<T: Any>(
         &mut self,
         callback: impl Fn(&mut Context, PersonId) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<PeoplePlugin>();
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
         data_container
             .creation_observers
             .borrow_mut()
@@ -110,7 +442,7 @@ impl PeopleContext for Context {
     }
 
     fn ignore_person_creation<T: Any>(&mut self) {
-        let data_container = self.get_data_container_mut::<PeoplePlugin>();
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
         data_container
             .creation_observers
             .borrow_mut()
@@ -121,7 +453,7 @@ impl PeopleContext for Context {
         &mut self,
         callback: impl Fn(&mut Context, PersonId) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<PeoplePlugin>();
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
         data_container
             .creation_immediate_callbacks
             .borrow_mut()
@@ -129,19 +461,96 @@ impl PeopleContext for Context {
     }
 
     fn remove_immediate_creation_callback<T: Any>(&mut self) {
-        let data_container = self.get_data_container_mut::<PeoplePlugin>();
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
         data_container
             .creation_immediate_callbacks
             .borrow_mut()
             .remove(&TypeId::of::<T>());
     }
+
+    fn observe_people_batch_creation<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonIdRange) + 'static,
+    ) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .batch_creation_observers
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(callback));
+    }
+
+    fn ignore_people_batch_creation<T: Any>(&mut self) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .batch_creation_observers
+            .borrow_mut()
+            .remove(&TypeId::of::<T>());
+    }
+
+    fn add_immediate_batch_creation_callback<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonIdRange) + 'static,
+    ) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .batch_creation_immediate_callbacks
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(callback));
+    }
+
+    fn remove_immediate_batch_creation_callback<T: Any>(&mut self) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .batch_creation_immediate_callbacks
+            .borrow_mut()
+            .remove(&TypeId::of::<T>());
+    }
+
+    fn observe_person_removal<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonId) + 'static,
+    ) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .removal_observers
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(callback));
+    }
+
+    fn ignore_person_removal<T: Any>(&mut self) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .removal_observers
+            .borrow_mut()
+            .remove(&TypeId::of::<T>());
+    }
+
+    fn add_immediate_removal_callback<T: Any>(
+        &mut self,
+        callback: impl Fn(&mut Context, PersonId) + 'static,
+    ) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .removal_immediate_callbacks
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), Rc::new(callback));
+    }
+
+    fn remove_immediate_removal_callback<T: Any>(&mut self) {
+        let data_container = self.get_or_init_data_container::<PeoplePlugin>();
+        data_container
+            .removal_immediate_callbacks
+            .borrow_mut()
+            .remove(&TypeId::of::<T>());
+    }
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
     use crate::context::Context;
 
-    use super::{PeopleContext, PersonId};
+    use super::{PeopleContext, PersonId, PersonIdRange};
 
     crate::context::define_plugin!(PluginA, Option<PersonId>, None);
 
@@ -155,7 +564,7 @@ mod tests {
 
         // Test observation callback (queued)
         assert_eq!(context.get_data_container_mut::<PluginA>(), &None);
-        context.add_person().execute();
+        context.add_person().execute().unwrap();
         assert_eq!(context.get_data_container_mut::<PluginA>(), &None);
         // Trigger callback in queue
         context.execute();
@@ -168,10 +577,135 @@ mod tests {
         context.add_immediate_creation_callback::<PluginA>(|context, person_id| {
             *context.get_data_container_mut::<PluginA>() = Some(person_id)
         });
-        context.add_person().execute();
+        context.add_person().execute().unwrap();
         assert_eq!(
             context.get_data_container::<PluginA>().unwrap(),
             &context.get_maximum_person_id()
         );
     }
+
+    #[test]
+    fn test_remove_person() {
+        let mut context = Context::new();
+        assert_eq!(context.get_population(), 0);
+        for _ in 0..3 {
+            context.add_person().execute().unwrap();
+        }
+        assert_eq!(context.get_population(), 3);
+
+        context.observe_person_removal::<PluginA>(|context, person_id| {
+            assert!(!context.is_person_alive(person_id));
+            *context.get_or_init_data_container::<PluginA>() = Some(person_id)
+        });
+        context.add_immediate_removal_callback::<PluginA>(|context, person_id| {
+            assert!(context.is_person_alive(person_id));
+        });
+        context.remove_person(PersonId::new(1));
+        assert!(context.is_person_alive(PersonId::new(0)));
+        assert!(!context.is_person_alive(PersonId::new(1)));
+        assert!(!context.is_person_alive(PersonId::new(3)));
+        assert_eq!(context.get_population(), 2);
+        assert_eq!(
+            context.iter_people().collect::<Vec<_>>(),
+            vec![PersonId::new(0), PersonId::new(2)]
+        );
+        // The maximum id is unchanged and ids are not reused
+        assert_eq!(context.get_maximum_person_id(), Some(PersonId::new(2)));
+        assert_eq!(context.add_person().execute().unwrap(), PersonId::new(3));
+
+        // Removal observers are queued
+        assert_eq!(context.get_data_container::<PluginA>(), None);
+        context.execute();
+        assert_eq!(
+            context.get_data_container::<PluginA>(),
+            Some(&Some(PersonId::new(1)))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Person 0 does not exist or was already removed")]
+    fn test_remove_person_twice() {
+        let mut context = Context::new();
+        let person_id = context.add_person().execute().unwrap();
+        context.remove_person(person_id);
+        context.remove_person(person_id);
+    }
+
+    crate::context::define_plugin!(BatchPlugin, Vec<PersonIdRange>, Vec::new());
+    crate::context::define_plugin!(CountPlugin, usize, 0);
+
+    #[test]
+    fn test_add_people() {
+        let mut context = Context::new();
+        context.add_person().execute().unwrap();
+        context.observe_people_batch_creation::<BatchPlugin>(|context, range| {
+            context
+                .get_or_init_data_container::<BatchPlugin>()
+                .push(range)
+        });
+        context.observe_person_creation::<CountPlugin>(|context, _| {
+            *context.get_or_init_data_container::<CountPlugin>() += 1
+        });
+        context.add_immediate_creation_callback::<CountPlugin>(|context, person_id| {
+            assert!(context.is_person_alive(person_id));
+        });
+        context.add_immediate_batch_creation_callback::<CountPlugin>(|context, range| {
+            assert_eq!(
+                context.get_maximum_person_id(),
+                Some(PersonId::new(range.end.id - 1))
+            );
+        });
+
+        let range = context.add_people(3).execute().unwrap();
+        assert_eq!(range, PersonIdRange::new(1, 4));
+        assert_eq!(range.iter().collect::<Vec<_>>().len(), 3);
+        assert!(range.contains(PersonId::new(3)));
+        assert!(!range.contains(PersonId::new(4)));
+        assert_eq!(context.get_population(), 4);
+        assert!(context.add_people(0).execute().unwrap().is_empty());
+
+        context.remove_immediate_creation_callback::<CountPlugin>();
+        context.add_person().execute().unwrap();
+        context.execute();
+        assert_eq!(
+            context.get_data_container::<BatchPlugin>(),
+            Some(&vec![PersonIdRange::new(1, 4), PersonIdRange::new(4, 5)])
+        );
+        assert_eq!(context.get_data_container::<CountPlugin>(), Some(&4));
+    }
+
+    crate::context::define_plugin!(CallbackOrder, Vec<String>, Vec::new());
+
+    fn record_callback(context: &mut Context, name: &str) {
+        context
+            .get_or_init_data_container::<CallbackOrder>()
+            .push(name.to_string());
+    }
+
+    #[test]
+    fn test_single_and_batch_creation_callbacks() {
+        let mut context = Context::new();
+        context.add_immediate_creation_callback::<PluginA>(|context, person_id| {
+            record_callback(context, &format!("person {}", person_id.id));
+        });
+        context.add_immediate_batch_creation_callback::<PluginA>(|context, range| {
+            record_callback(context, &format!("batch {}", range.start.id));
+        });
+        context.add_immediate_creation_callback::<CountPlugin>(|context, person_id| {
+            record_callback(context, &format!("count {}", person_id.id));
+        });
+
+        context.add_person().execute().unwrap();
+        let mut single = std::mem::take(context.get_or_init_data_container::<CallbackOrder>());
+        context.add_people(1).execute().unwrap();
+        let mut batch = std::mem::take(context.get_or_init_data_container::<CallbackOrder>());
+        // Per person callbacks run before batch callbacks, and both run
+        // when they were added with the same key
+        assert_eq!(single.pop().unwrap(), "batch 0");
+        single.sort();
+        assert_eq!(single, vec!["count 0", "person 0"]);
+        assert_eq!(batch.pop().unwrap(), "batch 1");
+        batch.sort();
+        assert_eq!(batch, vec!["count 1", "person 1"]);
+    }
 }
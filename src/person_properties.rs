@@ -1,17 +1,92 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::rc::Rc;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::checkpoint::CheckpointContext;
 use crate::context::Context;
+use crate::creation::CreationContext;
 use crate::data_containers::vector_heterogeneous_container::VecDataContainer;
 use crate::data_containers::PropertyWithDefault;
 use crate::partitions::{Partition, PartitionBuilder, PartitionUpdateCallbackProvider};
-use crate::people::{PersonBuilder, PersonId};
+use crate::people::{
+    check_person_not_removed, try_check_person_not_removed, PeopleBatchBuilder, PeopleContext,
+    PersonBuilder, PersonId, PersonIdRange, RemovedPersonError,
+};
+
+pub trait PersonProperty: PropertyWithDefault {
+    // Derived properties are computed from other person properties when
+    // read instead of being stored
+    fn is_derived() -> bool {
+        false
+    }
+
+    fn compute(_context: &Context, _person_id: PersonId) -> Option<Self::Value> {
+        None
+    }
+
+    // Whether a derived value is the same before and after a dependency is
+    // set, in which case its observers aren't called
+    fn is_unchanged(_previous: &Self::Value, _current: &Self::Value) -> bool {
+        false
+    }
+
+    // Computes the value a person starts with when it isn't set on creation
+    fn initialize(_context: &Context, _person_id: PersonId) -> Option<Self::Value> {
+        None
+    }
+
+    // Visits the stored properties this property's value comes from, which
+    // is only itself unless it is derived
+    fn visit_stored_properties<V: PersonPropertyVisitor>(visitor: &mut V)
+    where
+        Self: Sized,
+    {
+        visitor.visit::<Self>();
+    }
+}
 
-pub trait PersonProperty: PropertyWithDefault {}
+pub trait PersonPropertyVisitor {
+    fn visit<T: PersonProperty>(&mut self);
+}
 
+// Defines a person property with a default value, or with an initializer
+// which computes each person's value when they are created, e.g.,
+// define_person_property!(Immunity, f64, initializer = |context, person_id| ...);
+// Initializers must be registered with register_person_property_initializer
+// before people are created. Otherwise there is no value to read, so reading
+// the property panics.
 #[macro_export]
 macro_rules! define_person_property {
+    ($person_property:ident, $value:ty, initializer = $initializer:expr) => {
+        pub struct $person_property {}
+
+        impl $crate::data_containers::PropertyWithDefault for $person_property {
+            type Value = $value;
+
+            fn get_default() -> Self::Value {
+                panic!(
+                    "Person property {} has an initializer instead of a default. Call register_person_property_initializer before people are created",
+                    stringify!($person_property)
+                )
+            }
+        }
+
+        impl $crate::person_properties::PersonProperty for $person_property {
+            fn initialize(
+                context: &$crate::context::Context,
+                person_id: $crate::people::PersonId,
+            ) -> Option<Self::Value> {
+                let initializer: fn(&$crate::context::Context, $crate::people::PersonId) -> $value =
+                    $initializer;
+                Some(initializer(context, person_id))
+            }
+        }
+    };
     ($person_property:ident, $value:ty, $default: expr) => {
         pub struct $person_property {}
 
@@ -52,11 +127,123 @@ macro_rules! define_person_property_from_enum {
 }
 pub use define_person_property_from_enum;
 
+// Defines a person property computed from the listed dependencies, e.g.,
+// define_derived_person_property!(AgeGroup, u8, [Age], |age| age / 10);
+// It can't be set, but is observed and used in partitions like any other
+// property. Observers are called when a dependency is set and the computed
+// value changes, so the value type must implement PartialEq.
+#[macro_export]
+macro_rules! define_derived_person_property {
+    ($person_property:ident, $value:ty, [$($dependency:ty),+], $compute:expr) => {
+        pub struct $person_property {}
+
+        impl $crate::data_containers::PropertyWithDefault for $person_property {
+            type Value = $value;
+
+            fn get_default() -> Self::Value {
+                panic!(
+                    "Derived person property {} has no default",
+                    stringify!($person_property)
+                )
+            }
+        }
+
+        impl $crate::person_properties::PersonProperty for $person_property {
+            fn is_derived() -> bool {
+                true
+            }
+
+            fn compute(
+                context: &$crate::context::Context,
+                person_id: $crate::people::PersonId,
+            ) -> Option<Self::Value> {
+                use $crate::person_properties::PersonPropertyContext;
+                let compute: fn(
+                    $(<$dependency as $crate::data_containers::PropertyWithDefault>::Value),+
+                ) -> $value = $compute;
+                Some(compute(
+                    $(context.get_person_property_value::<$dependency>(person_id)),+
+                ))
+            }
+
+            fn is_unchanged(previous: &Self::Value, current: &Self::Value) -> bool {
+                previous == current
+            }
+
+            fn visit_stored_properties<V: $crate::person_properties::PersonPropertyVisitor>(
+                visitor: &mut V,
+            ) {
+                $(
+                    <$dependency as $crate::person_properties::PersonProperty>::visit_stored_properties(
+                        visitor,
+                    );
+                )+
+            }
+        }
+    };
+}
+pub use define_derived_person_property;
+
+fn check_not_derived<T: PersonProperty>() {
+    if T::is_derived() {
+        panic!("Derived person property {} can't be set", type_name::<T>());
+    }
+}
+
+// Reads and stores values of a person property registered by name
+#[derive(Clone, Copy)]
+pub(crate) struct PersonPropertyRegistration {
+    pub(crate) parse: fn(&str) -> Result<Box<dyn Any>, String>,
+    pub(crate) type_id: TypeId,
+    // Sets the values for a batch, one per person
+    pub(crate) initialize:
+        for<'a> fn(PeopleBatchBuilder<'a>, Vec<Box<dyn Any>>) -> PeopleBatchBuilder<'a>,
+    pub(crate) reserve: fn(&mut Context, usize),
+}
+
+fn parse_person_property<T: PersonProperty>(value: &str) -> Result<Box<dyn Any>, String>
+where
+    T::Value: DeserializeOwned,
+{
+    let record = csv::StringRecord::from(vec![value]);
+    match record.deserialize::<T::Value>(None) {
+        Ok(value) => Ok(Box::new(value)),
+        Err(e) => match e.into_kind() {
+            csv::ErrorKind::Deserialize { err, .. } => Err(err.kind().to_string()),
+            kind => Err(format!("{:?}", kind)),
+        },
+    }
+}
+
+fn initialize_person_property<T: PersonProperty>(
+    builder: PeopleBatchBuilder<'_>,
+    values: Vec<Box<dyn Any>>,
+) -> PeopleBatchBuilder<'_> {
+    builder.set_person_property_values::<T>(
+        values
+            .into_iter()
+            .map(|value| *value.downcast::<T::Value>().unwrap())
+            .collect(),
+    )
+}
+
+fn reserve_person_property<T: PersonProperty>(context: &mut Context, len: usize) {
+    let data_container = context.get_or_init_data_container::<PersonPropertyPlugin>();
+    data_container.person_property_container.reserve::<T>(len);
+}
+
 struct PersonPropertyDataContainer {
     person_property_container: VecDataContainer,
     person_property_change_callbacks: HashMap<TypeId, Box<dyn Any>>,
     partition_update_callback_providers:
         HashMap<TypeId, HashMap<TypeId, Box<PartitionUpdateCallbackProvider>>>,
+    // Maps the names used in population files to properties
+    person_property_registrations: HashMap<String, PersonPropertyRegistration>,
+    // Maps each stored property to the observed derived properties which
+    // depend on it
+    derived_change_providers: HashMap<TypeId, HashMap<TypeId, DerivedChangeProvider>>,
+    // Run in registration order whenever people are created
+    person_property_initializers: Vec<(TypeId, PersonPropertyInitializer)>,
 }
 
 crate::context::define_plugin!(
@@ -66,15 +253,164 @@ crate::context::define_plugin!(
         person_property_container: VecDataContainer::new(),
         person_property_change_callbacks: HashMap::new(),
         partition_update_callback_providers: HashMap::new(),
+        person_property_registrations: HashMap::new(),
+        derived_change_providers: HashMap::new(),
+        person_property_initializers: Vec::new(),
     }
 );
 
+type PersonPropertyInitializer = fn(&mut Context, PersonIdRange);
+
+fn initialize_person_property_range<T: PersonProperty>(
+    context: &mut Context,
+    range: PersonIdRange,
+) {
+    // Values set by the builder are already stored and are kept
+    let len = get_person_property_values::<T>(context).map_or(0, Vec::len);
+    for person_id in range.iter().filter(|person_id| person_id.id >= len) {
+        let value = T::initialize(context, person_id)
+            .unwrap_or_else(|| panic!("Person property {} has no initializer", type_name::<T>()));
+        let data_container = context.get_or_init_data_container::<PersonPropertyPlugin>();
+        data_container
+            .person_property_container
+            .set_value::<T>(person_id.id, value);
+    }
+}
+
+// Runs the registered initializers for newly created people, before any
+// creation callbacks see them
+pub(crate) fn initialize_person_properties(context: &mut Context, range: PersonIdRange) {
+    let Some(data_container) = context.get_data_container::<PersonPropertyPlugin>() else {
+        return;
+    };
+    let initializers: Vec<PersonPropertyInitializer> = data_container
+        .person_property_initializers
+        .iter()
+        .map(|(_, initializer)| *initializer)
+        .collect();
+    for initializer in initializers {
+        initializer(context, range);
+    }
+}
+
+// Returns the checkpoint sections needed to save every stored property
+pub(crate) fn get_required_checkpoint_sections(context: &Context) -> Vec<&'static str> {
+    match context.get_data_container::<PersonPropertyPlugin>() {
+        None => Vec::new(),
+        Some(data_container) => data_container
+            .person_property_container
+            .get_property_names()
+            .collect(),
+    }
+}
+
+pub(crate) fn get_person_property_registration(
+    context: &Context,
+    name: &str,
+) -> Option<PersonPropertyRegistration> {
+    context
+        .get_data_container::<PersonPropertyPlugin>()?
+        .person_property_registrations
+        .get(name)
+        .copied()
+}
+
+// Returns the stored values of T, indexed by PersonId. People past the
+// end of the vector have the default value.
+pub(crate) fn get_person_property_values<T: PersonProperty>(
+    context: &Context,
+) -> Option<&Vec<T::Value>> {
+    context
+        .get_data_container::<PersonPropertyPlugin>()?
+        .person_property_container
+        .get_values::<T>()
+}
+
 type ContextCallback = dyn FnOnce(&mut Context);
-type PersonPropertyChangeCallback<T> = dyn Fn(&mut Context, PersonId, T);
+// Passed to observers when a person property is set
+pub struct PersonPropertyChangeEvent<T: PersonProperty> {
+    pub person_id: PersonId,
+    pub previous: T::Value,
+    pub current: T::Value,
+}
+
+type PersonPropertyChangeCallback<T> = dyn Fn(&mut Context, PersonPropertyChangeEvent<T>);
+
+// Called before a dependency is set, returning a callback which queues the
+// change observers of a derived property once it has been set
+type DerivedChangeProvider = fn(&Context, PersonId) -> Box<ContextCallback>;
+
+fn get_derived_change_callback<T: PersonProperty>(
+    context: &Context,
+    person_id: PersonId,
+) -> Box<ContextCallback> {
+    let previous = context.get_person_property_value::<T>(person_id);
+    Box::new(move |context| {
+        let current = context.get_person_property_value::<T>(person_id);
+        if T::is_unchanged(&previous, &current) {
+            return;
+        }
+        let callback_vec: Vec<Rc<PersonPropertyChangeCallback<T>>> = context
+            .get_data_container::<PersonPropertyPlugin>()
+            .and_then(|data_container| {
+                data_container
+                    .person_property_change_callbacks
+                    .get(&TypeId::of::<T>())
+            })
+            .map(|callback_vec| callback_vec.downcast_ref::<Vec<_>>().unwrap().clone())
+            .unwrap_or_default();
+        for callback in callback_vec {
+            let previous = previous.clone();
+            let current = current.clone();
+            context.queue_callback(move |context| {
+                callback(
+                    context,
+                    PersonPropertyChangeEvent {
+                        person_id,
+                        previous,
+                        current,
+                    },
+                )
+            });
+        }
+    })
+}
+
+fn add_derived_change_provider<D: PersonProperty, T: PersonProperty>(context: &mut Context) {
+    let data_container = context.get_or_init_data_container::<PersonPropertyPlugin>();
+    data_container
+        .derived_change_providers
+        .entry(TypeId::of::<D>())
+        .or_default()
+        .insert(TypeId::of::<T>(), get_derived_change_callback::<T>);
+}
+
+// Collects the registrations needed to observe derived property T
+struct DerivedPropertyDependencies<T: PersonProperty> {
+    registrations: Vec<fn(&mut Context)>,
+    property: PhantomData<T>,
+}
+
+impl<T: PersonProperty> PersonPropertyVisitor for DerivedPropertyDependencies<T> {
+    fn visit<D: PersonProperty>(&mut self) {
+        self.registrations.push(add_derived_change_provider::<D, T>);
+    }
+}
 
 pub trait PersonPropertyContext {
+    // Panics if the person has been removed
     fn get_person_property_value<T: PersonProperty>(&self, person_id: PersonId) -> T::Value;
 
+    // Returns an error instead of panicking if the person has been removed
+    fn try_get_person_property_value<T: PersonProperty>(
+        &self,
+        person_id: PersonId,
+    ) -> Result<T::Value, RemovedPersonError>;
+
+    // Reads the value without cloning it. Values which have never been set
+    // are the property's default, which is returned owned.
+    fn get_person_property_ref<T: PersonProperty>(&self, person_id: PersonId) -> Cow<'_, T::Value>;
+
     fn set_person_property_value<T: PersonProperty>(
         &mut self,
         person_id: PersonId,
@@ -83,7 +419,7 @@ pub trait PersonPropertyContext {
 
     fn observe_person_property_changes<T: PersonProperty>(
         &mut self,
-        callback: impl Fn(&mut Context, PersonId, T::Value) + 'static,
+        callback: impl Fn(&mut Context, PersonPropertyChangeEvent<T>) + 'static,
     );
 
     fn add_person_property_partition_callback<T: PersonProperty, K: Partition>(
@@ -92,16 +428,64 @@ pub trait PersonPropertyContext {
     );
 
     fn remove_person_property_partition_callback<T: PersonProperty, K: Partition>(&mut self);
+
+    fn register_checkpoint_person_property<T: PersonProperty>(&mut self)
+    where
+        T::Value: Serialize + DeserializeOwned;
+
+    // Registers person property T under a name used in population files
+    fn register_person_property<T: PersonProperty>(&mut self, name: &str)
+    where
+        T::Value: DeserializeOwned;
+
+    // Requires T to be set when each person is created
+    fn require_person_property<T: PersonProperty>(&mut self);
+
+    // Computes T with its initializer for each person created afterwards
+    // who wasn't given a value by their builder. The value is stored, so it
+    // doesn't change between reads.
+    fn register_person_property_initializer<T: PersonProperty>(&mut self);
 }
 
 impl PersonPropertyContext for Context {
     fn get_person_property_value<T: PersonProperty>(&self, person_id: PersonId) -> T::Value {
+        match self.try_get_person_property_value::<T>(person_id) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_get_person_property_value<T: PersonProperty>(
+        &self,
+        person_id: PersonId,
+    ) -> Result<T::Value, RemovedPersonError> {
+        try_check_person_not_removed(self, person_id)?;
+        if let Some(value) = T::compute(self, person_id) {
+            return Ok(value);
+        }
         let data_container = self.get_data_container::<PersonPropertyPlugin>();
-        match data_container {
+        Ok(match data_container {
             None => T::get_default(),
             Some(data_container) => data_container
                 .person_property_container
                 .get_value::<T>(person_id.id),
+        })
+    }
+
+    fn get_person_property_ref<T: PersonProperty>(&self, person_id: PersonId) -> Cow<'_, T::Value> {
+        check_person_not_removed(self, person_id);
+        if let Some(value) = T::compute(self, person_id) {
+            return Cow::Owned(value);
+        }
+        match self
+            .get_data_container::<PersonPropertyPlugin>()
+            .and_then(|data_container| {
+                data_container
+                    .person_property_container
+                    .get_value_ref::<T>(person_id.id)
+            }) {
+            None => Cow::Owned(T::get_default()),
+            Some(value) => Cow::Borrowed(value),
         }
     }
 
@@ -110,24 +494,36 @@ impl PersonPropertyContext for Context {
         person_id: PersonId,
         value: T::Value,
     ) {
+        check_not_derived::<T>();
+        check_person_not_removed(self, person_id);
         let mut callbacks_to_add = Vec::<Box<ContextCallback>>::new();
         let mut partition_callbacks = Vec::new();
+        let mut derived_callbacks = Vec::new();
         if let Some(data_container) = self.get_data_container::<PersonPropertyPlugin>() {
             // Observation callbacks
             let callback_vec = data_container
                 .person_property_change_callbacks
                 .get(&TypeId::of::<T>());
-            if callback_vec.is_some() {
-                let callback_vec: &Vec<Rc<PersonPropertyChangeCallback<T::Value>>> =
-                    callback_vec.unwrap().downcast_ref().unwrap();
+            if let Some(callback_vec) = callback_vec {
+                let callback_vec: &Vec<Rc<PersonPropertyChangeCallback<T>>> =
+                    callback_vec.downcast_ref().unwrap();
                 if !callback_vec.is_empty() {
-                    let current_value = data_container
+                    let previous_value = data_container
                         .person_property_container
                         .get_value::<T>(person_id.id);
                     for callback in callback_vec {
                         let internal_callback = Rc::clone(callback);
+                        let previous = previous_value.clone();
+                        let current = value.clone();
                         callbacks_to_add.push(Box::new(move |context| {
-                            internal_callback(context, person_id, current_value)
+                            internal_callback(
+                                context,
+                                PersonPropertyChangeEvent {
+                                    person_id,
+                                    previous,
+                                    current,
+                                },
+                            )
                         }));
                     }
                 }
@@ -136,20 +532,28 @@ impl PersonPropertyContext for Context {
             let partition_callback_map = data_container
                 .partition_update_callback_providers
                 .get(&TypeId::of::<T>());
-            if partition_callback_map.is_some() {
-                let partition_callback_map = partition_callback_map.unwrap();
+            if let Some(partition_callback_map) = partition_callback_map {
                 for entry in partition_callback_map {
                     let partition_update_callback = (entry.1)(self, person_id);
                     partition_callbacks.push(partition_update_callback);
                 }
             }
+            // Observed derived properties
+            if let Some(providers) = data_container
+                .derived_change_providers
+                .get(&TypeId::of::<T>())
+            {
+                for provider in providers.values() {
+                    derived_callbacks.push(provider(self, person_id));
+                }
+            }
         }
 
         for callback in callbacks_to_add {
             self.queue_callback(callback);
         }
 
-        let data_container = self.get_data_container_mut::<PersonPropertyPlugin>();
+        let data_container = self.get_or_init_data_container::<PersonPropertyPlugin>();
         data_container
             .person_property_container
             .set_value::<T>(person_id.id, value);
@@ -158,27 +562,42 @@ impl PersonPropertyContext for Context {
         for partition_callback in partition_callbacks {
             partition_callback(self)
         }
+
+        for derived_callback in derived_callbacks {
+            derived_callback(self);
+        }
     }
 
     fn observe_person_property_changes<T: PersonProperty>(
         &mut self,
-        callback: impl Fn(&mut Context, PersonId, T::Value) + 'static,
+        callback: impl Fn(&mut Context, PersonPropertyChangeEvent<T>) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<PersonPropertyPlugin>();
+        let data_container = self.get_or_init_data_container::<PersonPropertyPlugin>();
         let callback_vec = data_container
             .person_property_change_callbacks
             .entry(TypeId::of::<T>())
-            .or_insert_with(|| Box::<Vec<Rc<PersonPropertyChangeCallback<T::Value>>>>::default());
-        let callback_vec: &mut Vec<Rc<PersonPropertyChangeCallback<T::Value>>> =
+            .or_insert_with(|| Box::<Vec<Rc<PersonPropertyChangeCallback<T>>>>::default());
+        let callback_vec: &mut Vec<Rc<PersonPropertyChangeCallback<T>>> =
             callback_vec.downcast_mut().unwrap();
         callback_vec.push(Rc::new(callback));
+
+        if T::is_derived() {
+            let mut dependencies = DerivedPropertyDependencies::<T> {
+                registrations: Vec::new(),
+                property: PhantomData,
+            };
+            T::visit_stored_properties(&mut dependencies);
+            for registration in dependencies.registrations {
+                registration(self);
+            }
+        }
     }
 
     fn add_person_property_partition_callback<T: PersonProperty, K: Partition>(
         &mut self,
         provider: impl (Fn(&Context, PersonId) -> Box<dyn Fn(&mut Context)>) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<PersonPropertyPlugin>();
+        let data_container = self.get_or_init_data_container::<PersonPropertyPlugin>();
         let provider_map = data_container
             .partition_update_callback_providers
             .entry(TypeId::of::<T>())
@@ -187,7 +606,7 @@ impl PersonPropertyContext for Context {
     }
 
     fn remove_person_property_partition_callback<T: PersonProperty, K: Partition>(&mut self) {
-        let data_container = self.get_data_container_mut::<PersonPropertyPlugin>();
+        let data_container = self.get_or_init_data_container::<PersonPropertyPlugin>();
         let provider_map = data_container
             .partition_update_callback_providers
             .get_mut(&TypeId::of::<T>());
@@ -195,6 +614,69 @@ impl PersonPropertyContext for Context {
             provider_map.remove(&TypeId::of::<K>());
         }
     }
+
+    fn register_checkpoint_person_property<T: PersonProperty>(&mut self)
+    where
+        T::Value: Serialize + DeserializeOwned,
+    {
+        self.add_checkpoint_section(
+            type_name::<T>(),
+            |context| match context.get_data_container::<PersonPropertyPlugin>() {
+                None => Vec::new(),
+                Some(data_container) => data_container
+                    .person_property_container
+                    .get_values::<T>()
+                    .cloned()
+                    .unwrap_or_default(),
+            },
+            |context, values: Vec<T::Value>| {
+                let data_container = context.get_or_init_data_container::<PersonPropertyPlugin>();
+                data_container
+                    .person_property_container
+                    .set_values::<T>(values);
+            },
+        );
+    }
+
+    fn register_person_property<T: PersonProperty>(&mut self, name: &str)
+    where
+        T::Value: DeserializeOwned,
+    {
+        let data_container = self.get_or_init_data_container::<PersonPropertyPlugin>();
+        data_container.person_property_registrations.insert(
+            name.to_string(),
+            PersonPropertyRegistration {
+                parse: parse_person_property::<T>,
+                type_id: TypeId::of::<T>(),
+                initialize: initialize_person_property::<T>,
+                reserve: reserve_person_property::<T>,
+            },
+        );
+    }
+
+    fn require_person_property<T: PersonProperty>(&mut self) {
+        self.require_initializer::<PersonId, T>();
+    }
+
+    fn register_person_property_initializer<T: PersonProperty>(&mut self) {
+        if self.get_maximum_person_id().is_some() {
+            panic!(
+                "Initializer for person property {} must be registered before people are created",
+                type_name::<T>()
+            );
+        }
+        let data_container = self.get_or_init_data_container::<PersonPropertyPlugin>();
+        let type_id = TypeId::of::<T>();
+        if !data_container
+            .person_property_initializers
+            .iter()
+            .any(|(registered, _)| *registered == type_id)
+        {
+            data_container
+                .person_property_initializers
+                .push((type_id, initialize_person_property_range::<T>));
+        }
+    }
 }
 
 pub trait PersonPropertiesPersonBuilder<'a> {
@@ -203,8 +685,9 @@ pub trait PersonPropertiesPersonBuilder<'a> {
 
 impl<'a> PersonPropertiesPersonBuilder<'a> for PersonBuilder<'a> {
     fn set_person_property<T: PersonProperty>(mut self, value: T::Value) -> PersonBuilder<'a> {
-        self.add_callback(move |context, person_id: PersonId| {
-            let data_container = context.get_data_container_mut::<PersonPropertyPlugin>();
+        check_not_derived::<T>();
+        self.add_initializer::<T>(move |context, person_id: PersonId| {
+            let data_container = context.get_or_init_data_container::<PersonPropertyPlugin>();
             data_container
                 .person_property_container
                 .set_value::<T>(person_id.id, value);
@@ -213,18 +696,86 @@ impl<'a> PersonPropertiesPersonBuilder<'a> for PersonBuilder<'a> {
     }
 }
 
+pub trait PersonPropertiesPeopleBatchBuilder<'a> {
+    // Sets T for each person in the batch, in id order. There must be one
+    // value per person.
+    fn set_person_property_values<T: PersonProperty>(
+        self,
+        values: Vec<T::Value>,
+    ) -> PeopleBatchBuilder<'a>;
+}
+
+impl<'a> PersonPropertiesPeopleBatchBuilder<'a> for PeopleBatchBuilder<'a> {
+    fn set_person_property_values<T: PersonProperty>(
+        mut self,
+        values: Vec<T::Value>,
+    ) -> PeopleBatchBuilder<'a> {
+        check_not_derived::<T>();
+        if values.len() != self.get_count() {
+            panic!(
+                "Expected {} values for {} but got {}",
+                self.get_count(),
+                type_name::<T>(),
+                values.len()
+            );
+        }
+        self.add_initializer::<T>(move |context, range| {
+            let data_container = context.get_or_init_data_container::<PersonPropertyPlugin>();
+            data_container
+                .person_property_container
+                .set_value_range::<T>(range.start.id, values);
+        });
+        self
+    }
+}
+
 pub trait PersonPropertyPartitionBuilder<'a, P: Partition> {
     fn add_person_property_sensitivity<T: PersonProperty>(self) -> PartitionBuilder<'a, P>;
 }
 
+fn add_partition_sensitivity<T: PersonProperty, P: Partition>(context: &mut Context) {
+    context.add_person_property_partition_callback::<T, P>(P::get_update_callback_provider());
+}
+
+fn remove_partition_sensitivity<T: PersonProperty, P: Partition>(context: &mut Context) {
+    context.remove_person_property_partition_callback::<T, P>();
+}
+
+// Collects the registrations needed for partition P to follow a property,
+// which for a derived property means following its dependencies
+struct PartitionSensitivities<P: Partition> {
+    registrations: Vec<fn(&mut Context)>,
+    deregistrations: Vec<fn(&mut Context)>,
+    partition: PhantomData<P>,
+}
+
+impl<P: Partition> PersonPropertyVisitor for PartitionSensitivities<P> {
+    fn visit<T: PersonProperty>(&mut self) {
+        self.registrations.push(add_partition_sensitivity::<T, P>);
+        self.deregistrations
+            .push(remove_partition_sensitivity::<T, P>);
+    }
+}
+
 impl<'a, P: Partition> PersonPropertyPartitionBuilder<'a, P> for PartitionBuilder<'a, P> {
     fn add_person_property_sensitivity<T: PersonProperty>(mut self) -> PartitionBuilder<'a, P> {
-        self.add_registration_callback(|context| {
-            context
-                .add_person_property_partition_callback::<T, P>(P::get_update_callback_provider());
+        let mut sensitivities = PartitionSensitivities::<P> {
+            registrations: Vec::new(),
+            deregistrations: Vec::new(),
+            partition: PhantomData,
+        };
+        T::visit_stored_properties(&mut sensitivities);
+        let (registrations, deregistrations) =
+            (sensitivities.registrations, sensitivities.deregistrations);
+        self.add_registration_callback(move |context| {
+            for registration in registrations {
+                registration(context);
+            }
         });
-        self.add_deregistration_callback(|context| {
-            context.remove_person_property_partition_callback::<T, P>();
+        self.add_deregistration_callback(move |context| {
+            for deregistration in deregistrations {
+                deregistration(context);
+            }
         });
         self
     }
@@ -233,10 +784,15 @@ impl<'a, P: Partition> PersonPropertyPartitionBuilder<'a, P> for PartitionBuilde
 #[cfg(test)]
 mod test {
     use crate::context::{Component, Context};
-    use crate::people::PeopleContext;
+    use crate::data_containers::PersonContainer;
+    use crate::partitions::{Partition, PartitionContext};
+    use crate::people::{PeopleContext, RemovedPersonError};
     use crate::person_properties::{
-        PersonId, PersonPropertiesPersonBuilder, PersonPropertyContext,
+        PersonId, PersonPropertiesPeopleBatchBuilder, PersonPropertiesPersonBuilder,
+        PersonPropertyChangeEvent, PersonPropertyContext, PersonPropertyPartitionBuilder,
     };
+    use crate::random::RandomContext;
+    use rand::Rng;
 
     define_person_property!(PropertyOne, usize, 0);
 
@@ -279,10 +835,11 @@ mod test {
     impl ComponentA {
         fn handle_person_property_value_assignment(
             context: &mut Context,
-            person_id: PersonId,
-            _value: usize,
+            event: PersonPropertyChangeEvent<PropertyOne>,
         ) {
-            context.set_person_property_value::<PropertyTwo>(person_id, PropertyTwo::B);
+            assert_eq!(event.previous, 0);
+            assert_eq!(event.current, 1);
+            context.set_person_property_value::<PropertyTwo>(event.person_id, PropertyTwo::B);
         }
 
         fn set_person_0_property_one_to_1(context: &mut Context) {
@@ -328,7 +885,8 @@ mod test {
             .add_person()
             .set_person_property::<PropertyOne>(1)
             .set_person_property::<PropertyTwo>(PropertyTwo::B)
-            .execute();
+            .execute()
+            .unwrap();
         assert_eq!(context.get_person_property_value::<PropertyOne>(person), 1);
         assert!(matches!(
             context.get_person_property_value::<PropertyTwo>(person),
@@ -336,4 +894,247 @@ mod test {
         ));
         assert_eq!(context.get_maximum_person_id(), Some(PersonId::new(0)));
     }
+
+    define_person_property!(Name, String, String::from("Unknown"));
+    define_person_property!(VaccineDoses, Vec<f64>, Vec::new());
+
+    #[test]
+    fn test_non_copy_values() {
+        let mut context = Context::new();
+        let person = context
+            .add_person()
+            .set_person_property::<Name>(String::from("Alice"))
+            .execute()
+            .unwrap();
+        let other_person = context.add_person().execute().unwrap();
+        assert_eq!(context.get_person_property_value::<Name>(person), "Alice");
+        assert_eq!(
+            *context.get_person_property_ref::<Name>(other_person),
+            "Unknown"
+        );
+
+        context.observe_person_property_changes::<VaccineDoses>(|context, event| {
+            assert_eq!(event.previous.len() + 1, event.current.len());
+            context.set_person_property_value::<Name>(event.person_id, String::from("Vaccinated"));
+        });
+        context.set_person_property_value::<VaccineDoses>(person, vec![10.0]);
+        context.set_person_property_value::<VaccineDoses>(person, vec![10.0, 31.0]);
+        context.execute();
+        assert_eq!(
+            context
+                .get_person_property_ref::<VaccineDoses>(person)
+                .as_slice(),
+            &[10.0, 31.0]
+        );
+        assert!(context
+            .get_person_property_ref::<VaccineDoses>(other_person)
+            .is_empty());
+        assert_eq!(
+            context.get_person_property_value::<Name>(person),
+            "Vaccinated"
+        );
+    }
+
+    define_person_property!(Age, u8, 0);
+    define_person_property!(Vaccinated, bool, false);
+    define_derived_person_property!(AgeGroup, u8, [Age], |age| age / 10);
+    define_derived_person_property!(
+        IsEligible,
+        bool,
+        [AgeGroup, Vaccinated],
+        |age_group, vaccinated| age_group >= 6 && !vaccinated
+    );
+
+    struct EligiblePartition {}
+    impl Partition for EligiblePartition {
+        type LabelType = bool;
+    }
+
+    crate::context::define_plugin!(EligibilityChanges, Vec<(PersonId, bool, bool)>, Vec::new());
+
+    #[test]
+    fn test_derived_property() {
+        let mut context = Context::new();
+        let person = context
+            .add_person()
+            .set_person_property::<Age>(64)
+            .execute()
+            .unwrap();
+        context.add_person().execute().unwrap();
+        assert_eq!(context.get_person_property_value::<AgeGroup>(person), 6);
+        assert!(context.get_person_property_value::<IsEligible>(person));
+        assert!(*context.get_person_property_ref::<IsEligible>(person));
+
+        context
+            .add_partition::<EligiblePartition>()
+            .set_label_function(|context, person_id| {
+                context.get_person_property_value::<IsEligible>(person_id)
+            })
+            .add_person_property_sensitivity::<IsEligible>()
+            .execute();
+        assert_eq!(
+            context
+                .get_partition_cell::<EligiblePartition>(true)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        context.observe_person_property_changes::<IsEligible>(|context, event| {
+            context
+                .get_or_init_data_container::<EligibilityChanges>()
+                .push((event.person_id, event.previous, event.current));
+        });
+        // Setting a dependency of a dependency updates the partition and
+        // fires observers
+        context.set_person_property_value::<Vaccinated>(person, true);
+        context.set_person_property_value::<Age>(PersonId::new(1), 70);
+        // Setting a dependency without changing the derived value doesn't
+        // fire observers
+        context.set_person_property_value::<Age>(PersonId::new(1), 75);
+        context.set_person_property_value::<Age>(person, 66);
+        context.execute();
+        assert_eq!(
+            context.get_data_container::<EligibilityChanges>(),
+            Some(&vec![
+                (person, true, false),
+                (PersonId::new(1), false, true)
+            ])
+        );
+        let eligible_people = context
+            .get_partition_cell::<EligiblePartition>(true)
+            .unwrap();
+        assert_eq!(eligible_people.len(), 1);
+        assert!(eligible_people.contains(&PersonId::new(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be set")]
+    fn test_set_derived_property() {
+        let mut context = Context::new();
+        let person = context.add_person().execute().unwrap();
+        context.set_person_property_value::<AgeGroup>(person, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Person 0 has been removed")]
+    fn test_removed_person() {
+        let mut context = Context::new();
+        let person = context
+            .add_person()
+            .set_person_property::<PropertyOne>(1)
+            .execute()
+            .unwrap();
+        context.remove_person(person);
+        context.get_person_property_value::<PropertyOne>(person);
+    }
+
+    #[test]
+    fn test_try_get_removed_person() {
+        let mut context = Context::new();
+        let person = context
+            .add_person()
+            .set_person_property::<PropertyOne>(1)
+            .execute()
+            .unwrap();
+        assert_eq!(
+            context.try_get_person_property_value::<PropertyOne>(person),
+            Ok(1)
+        );
+        context.remove_person(person);
+        assert_eq!(
+            context.try_get_person_property_value::<PropertyOne>(person),
+            Err(RemovedPersonError { person_id: person })
+        );
+    }
+
+    crate::define_random_id!(ImmunityRng);
+
+    define_person_property!(
+        Immunity,
+        f64,
+        initializer = |context, _| context.get_rng::<ImmunityRng>().gen_range(0.0..1.0)
+    );
+    define_person_property!(
+        DoublePropertyOne,
+        usize,
+        initializer = |context, person_id| {
+            context.get_person_property_value::<PropertyOne>(person_id) * 2
+        }
+    );
+
+    #[test]
+    fn test_initializer() {
+        let mut context = Context::new();
+        context.set_base_random_seed(42);
+        context.register_person_property_initializer::<Immunity>();
+        context.register_person_property_initializer::<DoublePropertyOne>();
+
+        // Initializers see the values set by the builder
+        let person = context
+            .add_person()
+            .set_person_property::<PropertyOne>(3)
+            .execute()
+            .unwrap();
+        assert_eq!(
+            context.get_person_property_value::<DoublePropertyOne>(person),
+            6
+        );
+        let immunity = context.get_person_property_value::<Immunity>(person);
+        assert!((0.0..1.0).contains(&immunity));
+        assert_eq!(
+            context.get_person_property_value::<Immunity>(person),
+            immunity
+        );
+
+        // Values set by the builder aren't replaced
+        let person = context
+            .add_person()
+            .set_person_property::<Immunity>(2.0)
+            .execute()
+            .unwrap();
+        assert_eq!(context.get_person_property_value::<Immunity>(person), 2.0);
+
+        let range = context
+            .add_people(3)
+            .set_person_property_values::<DoublePropertyOne>(vec![1, 2, 3])
+            .execute()
+            .unwrap();
+        let immunities: Vec<f64> = range
+            .iter()
+            .map(|person_id| context.get_person_property_value::<Immunity>(person_id))
+            .collect();
+        assert!(immunities
+            .iter()
+            .all(|immunity| (0.0..1.0).contains(immunity)));
+        assert_ne!(immunities[0], immunities[1]);
+        for (person_id, immunity) in range.iter().zip(immunities) {
+            assert_eq!(
+                context.get_person_property_value::<Immunity>(person_id),
+                immunity
+            );
+        }
+        assert_eq!(
+            context.get_person_property_value::<DoublePropertyOne>(range.start),
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Person property Immunity has an initializer instead of a default. Call register_person_property_initializer before people are created"
+    )]
+    fn test_initializer_not_registered() {
+        let mut context = Context::new();
+        let person = context.add_person().execute().unwrap();
+        context.get_person_property_value::<Immunity>(person);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be registered before people are created")]
+    fn test_initializer_registered_late() {
+        let mut context = Context::new();
+        context.add_person().execute().unwrap();
+        context.register_person_property_initializer::<Immunity>();
+    }
 }
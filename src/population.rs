@@ -0,0 +1,630 @@
+use crate::context::Context;
+use crate::creation::{check_required_initializers, CreationError};
+use crate::groups::{GroupId, GroupType, GroupsContext};
+use crate::partitions::pause_partition_updates;
+use crate::people::{PeopleContext, PersonId, PersonIdRange};
+use crate::person_properties::{get_person_property_registration, PersonPropertyRegistration};
+use crate::regions::{reserve_person_regions, RegionId, RegionsContext, RegionsPeopleBatchBuilder};
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum PopulationLoadError {
+    // The file could not be read or is not valid CSV
+    Csv(csv::Error),
+    // Columns which are not registered person properties, the region
+    // column, a group column or ignored
+    UnknownColumns(Vec<String>),
+    // Region or group columns which are not in the file
+    MissingColumns(Vec<String>),
+    // A value could not be read. row is the line number in the file.
+    InvalidValue {
+        row: u64,
+        column: String,
+        message: String,
+    },
+    // A row is missing values which are required when people are created
+    Incomplete {
+        row: u64,
+        error: CreationError,
+    },
+}
+
+impl fmt::Display for PopulationLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PopulationLoadError::Csv(e) => write!(f, "Population file error: {}", e),
+            PopulationLoadError::UnknownColumns(columns) => {
+                write!(f, "Unknown population columns: {}", columns.join(", "))
+            }
+            PopulationLoadError::MissingColumns(columns) => {
+                write!(f, "Missing population columns: {}", columns.join(", "))
+            }
+            PopulationLoadError::InvalidValue {
+                row,
+                column,
+                message,
+            } => write!(
+                f,
+                "Invalid value in row {} column {}: {}",
+                row, column, message
+            ),
+            PopulationLoadError::Incomplete { row, error } => {
+                write!(f, "Incomplete person in row {}: {}", row, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PopulationLoadError {}
+
+impl From<csv::Error> for PopulationLoadError {
+    fn from(e: csv::Error) -> Self {
+        PopulationLoadError::Csv(e)
+    }
+}
+
+// Adds people to groups of one type
+#[derive(Clone, Copy)]
+struct GroupAssignment {
+    type_id: TypeId,
+    add_group: fn(&mut Context) -> usize,
+    add_person: fn(&mut Context, PersonId, usize),
+}
+
+impl GroupAssignment {
+    fn new<T: GroupType>() -> GroupAssignment {
+        GroupAssignment {
+            type_id: TypeId::of::<T>(),
+            add_group: |context| context.add_group::<T>().id,
+            add_person: |context, person_id, group_id| {
+                context.add_person_to_group(person_id, GroupId::<T>::new(group_id));
+            },
+        }
+    }
+}
+
+// Maps the group type and the group id used in the file to the id of the
+// group created for it
+type GroupIdMap = HashMap<(TypeId, usize), usize>;
+
+// Adds the person to the group with the file's id, creating the group the
+// first time the id is seen
+fn assign_group(
+    context: &mut Context,
+    group_ids: &mut GroupIdMap,
+    person_id: PersonId,
+    assignment: GroupAssignment,
+    file_group_id: usize,
+) {
+    let group_id = *group_ids
+        .entry((assignment.type_id, file_group_id))
+        .or_insert_with(|| (assignment.add_group)(context));
+    (assignment.add_person)(context, person_id, group_id);
+}
+
+#[derive(Clone, Copy)]
+enum Column {
+    Property(PersonPropertyRegistration),
+    Region,
+    Group(GroupAssignment),
+    Ignored,
+}
+
+// The values read from one row, checked before anyone is added
+struct PersonRecord {
+    row: u64,
+    region: Option<RegionId>,
+    properties: Vec<(PersonPropertyRegistration, Box<dyn Any>)>,
+    groups: Vec<(GroupAssignment, usize)>,
+}
+
+impl PersonRecord {
+    // Whether the two rows set the same properties and region, so they
+    // can be added in one batch
+    fn has_same_columns(&self, other: &PersonRecord) -> bool {
+        self.region.is_some() == other.region.is_some()
+            && self.properties.len() == other.properties.len()
+            && self
+                .properties
+                .iter()
+                .zip(&other.properties)
+                .all(|((a, _), (b, _))| a.type_id == b.type_id)
+    }
+}
+
+// Adds the people in records, which all have the same columns, as one
+// batch
+fn add_people_batch(
+    context: &mut Context,
+    group_ids: &mut GroupIdMap,
+    records: Vec<PersonRecord>,
+) -> Result<PersonIdRange, PopulationLoadError> {
+    let row = records[0].row;
+    let has_region = records[0].region.is_some();
+    let registrations: Vec<PersonPropertyRegistration> = records[0]
+        .properties
+        .iter()
+        .map(|(registration, _)| *registration)
+        .collect();
+    let mut property_values: Vec<Vec<Box<dyn Any>>> = registrations
+        .iter()
+        .map(|_| Vec::with_capacity(records.len()))
+        .collect();
+    let mut region_ids = Vec::new();
+    let mut groups = Vec::with_capacity(records.len());
+    for person_record in records {
+        for (values, (_, value)) in property_values.iter_mut().zip(person_record.properties) {
+            values.push(value);
+        }
+        region_ids.extend(person_record.region);
+        groups.push(person_record.groups);
+    }
+
+    let mut builder = context.add_people(groups.len());
+    for (registration, values) in registrations.iter().zip(property_values) {
+        builder = (registration.initialize)(builder, values);
+    }
+    if has_region {
+        builder = builder.set_regions(region_ids);
+    }
+    let range = builder
+        .execute()
+        .map_err(|error| PopulationLoadError::Incomplete { row, error })?;
+    for (person_id, groups) in range.iter().zip(groups) {
+        for (assignment, file_group_id) in groups {
+            assign_group(context, group_ids, person_id, assignment, file_group_id);
+        }
+    }
+    Ok(range)
+}
+
+fn parse_id(value: &str) -> Result<usize, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|e: std::num::ParseIntError| e.to_string())
+}
+
+// Adds a person for each row of a CSV file. Columns are matched by name to
+// person properties registered with register_person_property, and to the
+// region and group columns set on the loader. Empty property or group
+// cells are skipped.
+#[derive(Default)]
+pub struct PopulationLoader {
+    region_column: Option<String>,
+    group_columns: HashMap<String, GroupAssignment>,
+    ignored_columns: HashSet<String>,
+}
+
+impl PopulationLoader {
+    pub fn new() -> PopulationLoader {
+        PopulationLoader::default()
+    }
+
+    // Reads each person's RegionId from the column. Every row must name a
+    // region which has already been added.
+    pub fn set_region_column(mut self, name: &str) -> PopulationLoader {
+        self.region_column = Some(name.to_string());
+        self
+    }
+
+    // Adds each person to a group of type T. A new group is created for
+    // each distinct id in the column, so the file's ids only need to be
+    // unique within the file, not match existing group ids.
+    pub fn add_group_column<T: GroupType>(mut self, name: &str) -> PopulationLoader {
+        self.group_columns
+            .insert(name.to_string(), GroupAssignment::new::<T>());
+        self
+    }
+
+    pub fn ignore_column(mut self, name: &str) -> PopulationLoader {
+        self.ignored_columns.insert(name.to_string());
+        self
+    }
+
+    pub fn load(
+        &self,
+        context: &mut Context,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<PersonId>, PopulationLoadError> {
+        self.load_from_reader(context, csv::Reader::from_path(path)?)
+    }
+
+    // Reads every row before adding anyone, so no people are added if
+    // the file has an error
+    pub fn load_from_reader<R: io::Read>(
+        &self,
+        context: &mut Context,
+        mut reader: csv::Reader<R>,
+    ) -> Result<Vec<PersonId>, PopulationLoadError> {
+        let headers = reader.headers()?.clone();
+        let columns = self.get_columns(context, &headers)?;
+
+        let max_region_id = context.get_maximum_region_id();
+        let mut records = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let row = record.position().map_or(0, |position| position.line());
+            let mut person_record = PersonRecord {
+                row,
+                region: None,
+                properties: Vec::new(),
+                groups: Vec::new(),
+            };
+            let mut initialized = HashSet::new();
+            for ((name, value), column) in headers.iter().zip(record.iter()).zip(&columns) {
+                let invalid_value = |message: String| PopulationLoadError::InvalidValue {
+                    row,
+                    column: name.to_string(),
+                    message,
+                };
+                match column {
+                    Column::Ignored => {}
+                    Column::Property(_) | Column::Group(_) if value.is_empty() => {}
+                    Column::Property(registration) => {
+                        let value = (registration.parse)(value).map_err(invalid_value)?;
+                        person_record.properties.push((*registration, value));
+                        initialized.insert(registration.type_id);
+                    }
+                    Column::Region => {
+                        let region_id = RegionId::new(parse_id(value).map_err(invalid_value)?);
+                        if max_region_id.is_none_or(|max_region_id| region_id > max_region_id) {
+                            return Err(invalid_value(format!(
+                                "Region {} does not exist",
+                                region_id.id
+                            )));
+                        }
+                        person_record.region = Some(region_id);
+                        initialized.insert(TypeId::of::<RegionId>());
+                    }
+                    Column::Group(assignment) => {
+                        let group_id = parse_id(value).map_err(invalid_value)?;
+                        person_record.groups.push((*assignment, group_id));
+                    }
+                }
+            }
+            check_required_initializers::<PersonId>(context, &initialized)
+                .map_err(|error| PopulationLoadError::Incomplete { row, error })?;
+            records.push(person_record);
+        }
+
+        // Preallocate storage for everyone in the file
+        let population_size = context
+            .get_maximum_person_id()
+            .map_or(0, |max_person_id| max_person_id.id + 1)
+            + records.len();
+        for column in &columns {
+            match column {
+                Column::Property(registration) => (registration.reserve)(context, population_size),
+                Column::Region => reserve_person_regions(context, population_size),
+                _ => {}
+            }
+        }
+
+        // Partitions are rebuilt once everyone has been added, or if adding
+        // people fails part way through
+        let mut context = pause_partition_updates(context);
+        let mut people = Vec::with_capacity(records.len());
+        let mut group_ids = GroupIdMap::new();
+        let mut records = records.into_iter().peekable();
+        while let Some(person_record) = records.next() {
+            // Consecutive rows which set the same columns are added together
+            let mut batch = vec![person_record];
+            while let Some(person_record) = records.next_if(|next| next.has_same_columns(&batch[0]))
+            {
+                batch.push(person_record);
+            }
+            people.extend(add_people_batch(&mut context, &mut group_ids, batch)?.iter());
+        }
+        Ok(people)
+    }
+
+    fn get_columns(
+        &self,
+        context: &Context,
+        headers: &csv::StringRecord,
+    ) -> Result<Vec<Column>, PopulationLoadError> {
+        let mut columns = Vec::new();
+        let mut unknown_columns = Vec::new();
+        for name in headers {
+            if self.region_column.as_deref() == Some(name) {
+                columns.push(Column::Region);
+            } else if let Some(assignment) = self.group_columns.get(name) {
+                columns.push(Column::Group(*assignment));
+            } else if self.ignored_columns.contains(name) {
+                columns.push(Column::Ignored);
+            } else if let Some(registration) = get_person_property_registration(context, name) {
+                columns.push(Column::Property(registration));
+            } else {
+                unknown_columns.push(name.to_string());
+            }
+        }
+        if !unknown_columns.is_empty() {
+            return Err(PopulationLoadError::UnknownColumns(unknown_columns));
+        }
+
+        let mut missing_columns: Vec<String> = self
+            .region_column
+            .iter()
+            .chain(self.group_columns.keys())
+            .filter(|name| !headers.iter().any(|header| header == name.as_str()))
+            .cloned()
+            .collect();
+        if !missing_columns.is_empty() {
+            missing_columns.sort();
+            return Err(PopulationLoadError::MissingColumns(missing_columns));
+        }
+        Ok(columns)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::context::Context;
+    use crate::data_containers::PersonContainer;
+    use crate::groups::{GroupId, GroupType, GroupsContext};
+    use crate::partitions::{Partition, PartitionContext};
+    use crate::people::{PeopleContext, PersonId};
+    use crate::person_properties::{
+        PersonPropertiesPersonBuilder, PersonPropertyContext, PersonPropertyPartitionBuilder,
+    };
+    use crate::population::{PopulationLoadError, PopulationLoader};
+    use crate::regions::{RegionId, RegionsContext, RegionsPersonBuilder};
+    use serde::Deserialize;
+
+    crate::define_person_property!(Age, u8, 0);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum RiskCategory {
+        High,
+        Low,
+    }
+    crate::define_person_property_from_enum!(RiskCategory, RiskCategory::Low);
+
+    #[derive(Hash, Eq, PartialEq)]
+    struct Household {}
+    impl GroupType for Household {}
+
+    struct AgePartition {}
+    impl Partition for AgePartition {
+        type LabelType = u8;
+    }
+
+    fn setup() -> Context {
+        let mut context = Context::new();
+        context.register_person_property::<Age>("age");
+        context.register_person_property::<RiskCategory>("risk");
+        context.add_region().execute().unwrap();
+        context.add_region().execute().unwrap();
+        context
+    }
+
+    fn load(context: &mut Context, data: &str) -> Result<Vec<PersonId>, PopulationLoadError> {
+        PopulationLoader::new()
+            .set_region_column("region")
+            .add_group_column::<Household>("household")
+            .ignore_column("name")
+            .load_from_reader(context, csv::Reader::from_reader(data.as_bytes()))
+    }
+
+    #[test]
+    fn test_load() {
+        let mut context = setup();
+        context
+            .add_partition::<AgePartition>()
+            .set_label_function(|context, person_id| {
+                context.get_person_property_value::<Age>(person_id)
+            })
+            .add_person_property_sensitivity::<Age>()
+            .execute();
+
+        let people = load(
+            &mut context,
+            "name,age,risk,region,household\n\
+             Alice,30,High,0,1\n\
+             Bob,30,,1,1\n\
+             Carol,5,Low,1,\n",
+        )
+        .unwrap();
+        assert_eq!(people.len(), 3);
+        assert_eq!(context.get_population(), 3);
+        assert_eq!(context.get_person_property_value::<Age>(people[0]), 30);
+        assert_eq!(
+            context.get_person_property_value::<RiskCategory>(people[0]),
+            RiskCategory::High
+        );
+        // Empty cells leave the default value
+        assert_eq!(
+            context.get_person_property_value::<RiskCategory>(people[1]),
+            RiskCategory::Low
+        );
+        assert_eq!(context.get_person_region(people[1]), RegionId::new(1));
+        assert_eq!(context.get_maximum_group_id::<Household>().unwrap().id, 0);
+        assert_eq!(
+            context
+                .get_group_members(GroupId::<Household>::new(0))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(context
+            .get_groups_for_person::<Household>(people[2])
+            .is_empty());
+
+        // Partitions include the loaded people and still track changes
+        assert_eq!(
+            context
+                .get_partition_cell::<AgePartition>(30)
+                .unwrap()
+                .len(),
+            2
+        );
+        context.set_person_property_value::<Age>(people[2], 30);
+        assert_eq!(
+            context
+                .get_partition_cell::<AgePartition>(30)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_group_ids() {
+        let mut context = setup();
+        context.add_group::<Household>();
+        let people = load(
+            &mut context,
+            "age,region,household\n\
+             30,0,1000\n\
+             31,0,7\n\
+             32,1,1000\n",
+        )
+        .unwrap();
+        // Only one group is created for each id in the file, after the
+        // existing group
+        assert_eq!(context.get_maximum_group_id::<Household>().unwrap().id, 2);
+        let household = GroupId::<Household>::new(1);
+        let members = context.get_group_members(household).unwrap();
+        assert_eq!(members.len(), 2);
+        assert!(members.contains(&people[0]) && members.contains(&people[2]));
+        assert_eq!(
+            context
+                .get_group_members(GroupId::<Household>::new(2))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Ids are mapped afresh in each file
+        load(&mut context, "age,region,household\n40,0,7\n").unwrap();
+        assert_eq!(context.get_maximum_group_id::<Household>().unwrap().id, 3);
+    }
+
+    #[test]
+    fn test_partition_updates_resume_after_panic() {
+        let mut context = setup();
+        context
+            .add_partition::<AgePartition>()
+            .set_label_function(|context, person_id| {
+                context.get_person_property_value::<Age>(person_id)
+            })
+            .add_person_property_sensitivity::<Age>()
+            .execute();
+        context.add_immediate_creation_callback::<Household>(|context, person_id| {
+            if context.get_person_property_value::<Age>(person_id) > 90 {
+                panic!("Age out of range");
+            }
+        });
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            load(
+                &mut context,
+                "age,region,household
+30,0,1
+95,0,1
+",
+            )
+        }));
+        assert!(result.is_err());
+
+        // Partitions were rebuilt and track people added afterwards
+        context.remove_immediate_creation_callback::<Household>();
+        context
+            .add_person()
+            .set_person_property::<Age>(30)
+            .set_region(RegionId::new(0))
+            .execute()
+            .unwrap();
+        assert_eq!(
+            context
+                .get_partition_cell::<AgePartition>(30)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let mut context = setup();
+        let result = load(
+            &mut context,
+            "name,age,risk,region,household\n\
+             Alice,30,High,0,1\n\
+             Bob,thirty,Low,1,1\n",
+        );
+        match result {
+            Err(PopulationLoadError::InvalidValue { row, column, .. }) => {
+                assert_eq!(row, 3);
+                assert_eq!(column, "age");
+            }
+            _ => panic!("Expected an invalid value error"),
+        }
+        // No one is added if a row has an error
+        assert_eq!(context.get_population(), 0);
+
+        let result = load(&mut context, "age,risk,region,household\n30,Medium,0,1\n");
+        assert!(matches!(
+            result,
+            Err(PopulationLoadError::InvalidValue { column, .. }) if column == "risk"
+        ));
+        let result = load(&mut context, "age,risk,region,household\n30,High,2,1\n");
+        assert!(matches!(
+            result,
+            Err(PopulationLoadError::InvalidValue { column, .. }) if column == "region"
+        ));
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut context = setup();
+        assert!(matches!(
+            load(&mut context, "age,weight,region,household\n30,70,0,1\n"),
+            Err(PopulationLoadError::UnknownColumns(columns)) if columns == vec!["weight"]
+        ));
+        assert!(matches!(
+            load(&mut context, "age\n30\n"),
+            Err(PopulationLoadError::MissingColumns(columns))
+                if columns == vec!["household", "region"]
+        ));
+    }
+
+    #[test]
+    fn test_required_property() {
+        let mut context = setup();
+        context.require_person_property::<RiskCategory>();
+        let result = load(
+            &mut context,
+            "age,risk,region,household\n30,High,0,1\n30,,0,1\n",
+        );
+        match result {
+            Err(PopulationLoadError::Incomplete { row, error }) => {
+                assert_eq!(row, 3);
+                assert_eq!(
+                    error.missing_initializers,
+                    vec![std::any::type_name::<RiskCategory>()]
+                );
+            }
+            _ => panic!("Expected an incomplete person error"),
+        }
+        assert_eq!(context.get_population(), 0);
+    }
+
+    #[test]
+    fn test_load_file() {
+        let mut context = setup();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("population.csv");
+        std::fs::write(&path, "age,region\n40,0\n41,1\n").unwrap();
+        let people = PopulationLoader::new()
+            .set_region_column("region")
+            .load(&mut context, &path)
+            .unwrap();
+        assert_eq!(people, vec![PersonId::new(0), PersonId::new(1)]);
+        assert_eq!(context.get_person_property_value::<Age>(people[1]), 41);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::context::Context;
+use crate::partitions::{Partition, PartitionBuilder, PartitionContext};
+use crate::people::{PeopleContext, PersonId};
+use crate::person_properties::{
+    PersonProperty, PersonPropertyContext, PersonPropertyPartitionBuilder,
+};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+// A tuple of person properties which people can be queried by, e.g.,
+// (Age, RiskCategory) with values (30, RiskCategory::High)
+pub trait PersonPropertyQuery: Any {
+    type Values: Any + Clone;
+
+    fn get_values(context: &Context, person_id: PersonId) -> Self::Values;
+
+    // Returns the people whose properties match by reading every value
+    fn scan(context: &Context, values: Self::Values) -> Vec<PersonId>;
+
+    fn add_sensitivities<P: Partition>(builder: PartitionBuilder<'_, P>)
+        -> PartitionBuilder<'_, P>;
+}
+
+macro_rules! impl_person_property_query {
+    ($($property:ident: $index:tt),+) => {
+        impl<$($property: PersonProperty),+> PersonPropertyQuery for ($($property,)+)
+        where
+            $($property::Value: PartialEq),+
+        {
+            type Values = ($($property::Value,)+);
+
+            fn get_values(context: &Context, person_id: PersonId) -> Self::Values {
+                ($(context.get_person_property_value::<$property>(person_id),)+)
+            }
+
+            fn scan(context: &Context, query_values: Self::Values) -> Vec<PersonId> {
+                context
+                    .iter_people()
+                    .filter(|person_id| {
+                        $(*context.get_person_property_ref::<$property>(*person_id)
+                            == query_values.$index)&&+
+                    })
+                    .collect()
+            }
+
+            fn add_sensitivities<P: Partition>(
+                builder: PartitionBuilder<'_, P>,
+            ) -> PartitionBuilder<'_, P> {
+                builder
+                    $(.add_person_property_sensitivity::<$property>())+
+            }
+        }
+    };
+}
+
+impl_person_property_query!(A: 0);
+impl_person_property_query!(A: 0, B: 1);
+impl_person_property_query!(A: 0, B: 1, C: 2);
+impl_person_property_query!(A: 0, B: 1, C: 2, D: 3);
+
+// The partition used to index people by the values of a query
+struct QueryPartition<Q: PersonPropertyQuery> {
+    query: PhantomData<Q>,
+}
+
+impl<Q: PersonPropertyQuery> Partition for QueryPartition<Q>
+where
+    Q::Values: Hash + Eq + Copy,
+{
+    type LabelType = Q::Values;
+}
+
+type IndexedQuery<Q> = fn(&Context, <Q as PersonPropertyQuery>::Values) -> Vec<PersonId>;
+
+fn query_index<Q: PersonPropertyQuery>(context: &Context, values: Q::Values) -> Vec<PersonId>
+where
+    Q::Values: Hash + Eq + Copy,
+{
+    match context.get_partition_cell::<QueryPartition<Q>>(values) {
+        None => Vec::new(),
+        Some(cell) => cell.iter().collect(),
+    }
+}
+
+struct QueryDataContainer {
+    // Maps TypeId::of::<Q>() to IndexedQuery<Q>
+    indexed_queries: HashMap<TypeId, Box<dyn Any>>,
+}
+
+crate::context::define_plugin!(
+    QueryPlugin,
+    QueryDataContainer,
+    QueryDataContainer {
+        indexed_queries: HashMap::new(),
+    }
+);
+
+pub trait QueryContext {
+    // Adds a partition on the properties in Q which query_people uses
+    // instead of scanning every person. Only this partition is used:
+    // other partitions which already cover Q aren't consulted
+    fn index_people_by_query<Q: PersonPropertyQuery>(&mut self)
+    where
+        Q::Values: Hash + Eq + Copy;
+
+    // Returns the people whose properties in Q have the given values.
+    // A scan returns them in id order, while an indexed query returns the
+    // partition cell's order, which isn't sorted
+    fn query_people<Q: PersonPropertyQuery>(&self, values: Q::Values) -> Vec<PersonId>;
+}
+
+impl QueryContext for Context {
+    fn index_people_by_query<Q: PersonPropertyQuery>(&mut self)
+    where
+        Q::Values: Hash + Eq + Copy,
+    {
+        let data_container = self.get_or_init_data_container::<QueryPlugin>();
+        if data_container
+            .indexed_queries
+            .contains_key(&TypeId::of::<Q>())
+        {
+            return;
+        }
+        let indexed_query: IndexedQuery<Q> = query_index::<Q>;
+        data_container
+            .indexed_queries
+            .insert(TypeId::of::<Q>(), Box::new(indexed_query));
+        let builder = self
+            .add_partition::<QueryPartition<Q>>()
+            .set_label_function(Q::get_values);
+        Q::add_sensitivities(builder).execute();
+    }
+
+    fn query_people<Q: PersonPropertyQuery>(&self, values: Q::Values) -> Vec<PersonId> {
+        let indexed_query = self
+            .get_data_container::<QueryPlugin>()
+            .and_then(|data_container| data_container.indexed_queries.get(&TypeId::of::<Q>()))
+            .map(|indexed_query| *indexed_query.downcast_ref::<IndexedQuery<Q>>().unwrap());
+        match indexed_query {
+            Some(indexed_query) => indexed_query(self, values),
+            None => Q::scan(self, values),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::context::Context;
+    use crate::people::{PeopleContext, PersonId};
+    use crate::person_properties::{PersonPropertiesPersonBuilder, PersonPropertyContext};
+    use crate::query::QueryContext;
+
+    crate::define_person_property!(Age, u8, 0);
+    crate::define_person_property!(Vaccinated, bool, false);
+    crate::define_person_property!(Weight, f64, 70.0);
+    crate::define_person_property!(Name, String, String::new());
+    crate::define_person_property!(
+        Household,
+        usize,
+        initializer = |_, person_id| person_id.id / 4
+    );
+
+    fn add_people(context: &mut Context) {
+        for age in 0..10 {
+            context
+                .add_person()
+                .set_person_property::<Age>(age % 3)
+                .set_person_property::<Vaccinated>(age % 2 == 0)
+                .execute()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_query_people() {
+        let mut context = Context::new();
+        add_people(&mut context);
+        assert_eq!(context.query_people::<(Age,)>((1,)).len(), 3);
+        assert_eq!(
+            context.query_people::<(Age, Vaccinated)>((1, true)),
+            vec![PersonId::new(4)]
+        );
+        context.remove_person(PersonId::new(4));
+        assert!(context
+            .query_people::<(Age, Vaccinated)>((1, true))
+            .is_empty());
+        // Values without a Hash implementation can still be scanned
+        assert_eq!(context.query_people::<(Weight,)>((70.0,)).len(), 9);
+
+        // As can values which aren't Copy
+        context.set_person_property_value::<Name>(PersonId::new(2), String::from("Bob"));
+        assert_eq!(
+            context.query_people::<(Name, Age)>((String::from("Bob"), 2)),
+            vec![PersonId::new(2)]
+        );
+        assert_eq!(context.query_people::<(Name,)>((String::new(),)).len(), 8);
+    }
+
+    #[test]
+    fn test_query_initialized_property() {
+        let mut context = Context::new();
+        context.register_person_property_initializer::<Household>();
+        add_people(&mut context);
+        assert_eq!(
+            context.query_people::<(Household, Age)>((2, 0)),
+            vec![PersonId::new(9)]
+        );
+        context.index_people_by_query::<(Household,)>();
+        assert_eq!(context.query_people::<(Household,)>((1,)).len(), 4);
+    }
+
+    #[test]
+    fn test_indexed_query() {
+        let mut context = Context::new();
+        add_people(&mut context);
+        let scanned = context.query_people::<(Age, Vaccinated)>((0, false));
+        context.index_people_by_query::<(Age, Vaccinated)>();
+        let mut indexed = context.query_people::<(Age, Vaccinated)>((0, false));
+        indexed.sort();
+        assert_eq!(indexed, scanned);
+
+        // The index is updated when properties change
+        context.set_person_property_value::<Vaccinated>(PersonId::new(3), true);
+        context.add_person().execute().unwrap();
+        let mut indexed = context.query_people::<(Age, Vaccinated)>((0, false));
+        indexed.sort();
+        assert_eq!(indexed, vec![PersonId::new(9), PersonId::new(10)]);
+        assert!(context
+            .query_people::<(Age, Vaccinated)>((3, false))
+            .is_empty());
+    }
+}
@@ -1,5 +1,8 @@
-use crate::context::Context;
+use crate::checkpoint::{CheckpointContext, CheckpointPlugin, CheckpointSection};
+use crate::context::{Context, PluginError};
 use rand::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::any::{Any, TypeId};
 use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
@@ -7,10 +10,13 @@ use std::collections::HashMap;
 #[macro_export]
 macro_rules! define_random_id {
     ($random_id:ident) => {
+        $crate::random::define_random_id!($random_id, rand::rngs::StdRng);
+    };
+    ($random_id:ident, $rng_type:ty) => {
         struct $random_id {}
 
         impl $crate::random::RandomId for $random_id {
-            type RngType = rand::rngs::StdRng;
+            type RngType = $rng_type;
 
             fn get_name() -> &'static str {
                 stringify!($random_id)
@@ -20,6 +26,11 @@ macro_rules! define_random_id {
 }
 pub use define_random_id;
 
+// An rng which produces the same stream as rand::rngs::StdRng but whose
+// state can be saved in a checkpoint, e.g.,
+// define_random_id!(TransmissionRng, CheckpointRng);
+pub type CheckpointRng = rand_chacha::ChaCha12Rng;
+
 pub trait RandomId: Any {
     type RngType: SeedableRng;
 
@@ -29,6 +40,7 @@ pub trait RandomId: Any {
 struct RandomHolder {
     rng: Box<dyn Any>,
     reseed: bool,
+    name: &'static str,
 }
 
 struct RandomData {
@@ -45,15 +57,59 @@ crate::context::define_plugin!(
     }
 );
 
+impl CheckpointPlugin for RandomPlugin {
+    type State = u64;
+
+    fn save_state(data_container: &RandomData) -> u64 {
+        data_container.base_seed
+    }
+
+    // Rngs without a registered checkpoint section are reseeded
+    fn load_state(data_container: &mut RandomData, base_seed: u64) {
+        data_container.base_seed = base_seed;
+        for random_holder in data_container.random_holders.get_mut().values_mut() {
+            random_holder.reseed = true;
+        }
+    }
+}
+
+pub(crate) fn get_checkpoint_section() -> CheckpointSection {
+    CheckpointSection::for_plugin::<RandomPlugin>()
+}
+
+// Returns the checkpoint sections needed to save every rng which has
+// been used since it was last seeded
+pub(crate) fn get_required_checkpoint_sections(context: &Context) -> Vec<&'static str> {
+    match context.get_data_container::<RandomPlugin>() {
+        None => Vec::new(),
+        Some(data_container) => data_container
+            .random_holders
+            .borrow()
+            .values()
+            .filter(|random_holder| !random_holder.reseed)
+            .map(|random_holder| random_holder.name)
+            .collect(),
+    }
+}
+
 pub trait RandomContext {
     fn set_base_random_seed(&mut self, base_seed: u64);
 
     fn get_rng<R: RandomId>(&self) -> RefMut<'_, R::RngType>;
+
+    // Returns an error instead of panicking if no base seed has been set
+    // or another rng is still borrowed
+    fn try_get_rng<R: RandomId>(&self) -> Result<RefMut<'_, R::RngType>, PluginError>;
+
+    // Saves the state of the rng for R in checkpoints
+    fn register_checkpoint_rng<R: RandomId>(&mut self)
+    where
+        R::RngType: Serialize + DeserializeOwned + Clone;
 }
 
 impl RandomContext for Context {
     fn set_base_random_seed(&mut self, base_seed: u64) {
-        let data_container = self.get_data_container_mut::<RandomPlugin>();
+        let data_container = self.get_or_init_data_container::<RandomPlugin>();
         data_container.base_seed = base_seed;
         let mut random_holders = data_container.random_holders.try_borrow_mut().unwrap();
         for random_holder in random_holders.values_mut() {
@@ -62,9 +118,22 @@ impl RandomContext for Context {
     }
 
     fn get_rng<R: RandomId>(&self) -> RefMut<'_, R::RngType> {
-        let data_container = self.get_data_container::<RandomPlugin>().unwrap();
+        match self.try_get_rng::<R>() {
+            Ok(rng) => rng,
+            Err(e @ PluginError::NotRegistered(_)) => {
+                panic!("{}; set a base random seed before using an rng", e)
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn try_get_rng<R: RandomId>(&self) -> Result<RefMut<'_, R::RngType>, PluginError> {
+        let data_container = self.try_get_data_container::<RandomPlugin>()?;
         let base_seed = data_container.base_seed;
-        let random_holders = data_container.random_holders.try_borrow_mut().unwrap();
+        let random_holders = data_container
+            .random_holders
+            .try_borrow_mut()
+            .map_err(|_| PluginError::AlreadyBorrowed(std::any::type_name::<RandomPlugin>()))?;
         let seed_offset = fxhash::hash64(R::get_name());
         let mut random_holder = RefMut::map(random_holders, |random_holders| {
             random_holders
@@ -72,27 +141,86 @@ impl RandomContext for Context {
                 .or_insert_with(|| RandomHolder {
                     rng: Box::new(R::RngType::seed_from_u64(base_seed + seed_offset)),
                     reseed: false,
+                    name: std::any::type_name::<R>(),
                 })
         });
         if random_holder.reseed {
             random_holder.rng = Box::new(R::RngType::seed_from_u64(base_seed + seed_offset));
             random_holder.reseed = false;
         }
-        RefMut::map(random_holder, |random_holder| {
+        Ok(RefMut::map(random_holder, |random_holder| {
             random_holder.rng.downcast_mut::<R::RngType>().unwrap()
-        })
+        }))
+    }
+
+    fn register_checkpoint_rng<R: RandomId>(&mut self)
+    where
+        R::RngType: Serialize + DeserializeOwned + Clone,
+    {
+        self.add_checkpoint_section::<Option<R::RngType>>(
+            std::any::type_name::<R>(),
+            |context| {
+                let data_container = context.get_data_container::<RandomPlugin>()?;
+                let random_holders = data_container.random_holders.borrow();
+                match random_holders.get(&TypeId::of::<R>()) {
+                    Some(random_holder) if !random_holder.reseed => {
+                        random_holder.rng.downcast_ref::<R::RngType>().cloned()
+                    }
+                    _ => None,
+                }
+            },
+            |context, rng| {
+                // The base seed section is loaded first and marks every rng
+                // for reseeding, so only saved rngs need to be restored
+                if let Some(rng) = rng {
+                    let data_container = context.get_or_init_data_container::<RandomPlugin>();
+                    data_container.random_holders.get_mut().insert(
+                        TypeId::of::<R>(),
+                        RandomHolder {
+                            rng: Box::new(rng),
+                            reseed: false,
+                            name: std::any::type_name::<R>(),
+                        },
+                    );
+                }
+            },
+        );
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::context::Context;
+    use crate::context::{Context, PluginError};
     use crate::random::RandomContext;
     use rand::RngCore;
 
     define_random_id!(RandomIdOne);
     define_random_id!(RandomIdTwo);
 
+    #[test]
+    #[should_panic(expected = "RandomPlugin has not been registered")]
+    fn test_rng_without_seed() {
+        let context = Context::new();
+        context.get_rng::<RandomIdOne>();
+    }
+
+    #[test]
+    fn test_try_get_rng() {
+        let mut context = Context::new();
+        assert!(matches!(
+            context.try_get_rng::<RandomIdOne>(),
+            Err(PluginError::NotRegistered(_))
+        ));
+        context.set_base_random_seed(8675309);
+        let rng_one = context.try_get_rng::<RandomIdOne>().unwrap();
+        assert!(matches!(
+            context.try_get_rng::<RandomIdTwo>(),
+            Err(PluginError::AlreadyBorrowed(_))
+        ));
+        drop(rng_one);
+        assert!(context.try_get_rng::<RandomIdTwo>().is_ok());
+    }
+
     #[test]
     fn test() {
         let mut context = Context::new();
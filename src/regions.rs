@@ -1,14 +1,17 @@
+use crate::checkpoint::{CheckpointContext, CheckpointPlugin, CheckpointSection};
 use crate::context::Context;
-use crate::creation::CreationBuilder;
+use crate::creation::{CreationBuilder, CreationContext};
 use crate::data_containers::vector_heterogeneous_container::VecDataContainer;
 use crate::data_containers::PropertyWithDefault;
 use crate::partitions::{Partition, PartitionBuilder, PartitionUpdateCallbackProvider};
-use crate::people::{PersonBuilder, PersonId};
-use std::any::TypeId;
+use crate::people::{check_person_not_removed, PeopleBatchBuilder, PersonBuilder, PersonId};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::any::{type_name, TypeId};
 use std::collections::HashMap;
 use std::rc::Rc;
 
-#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct RegionId {
     pub id: usize,
 }
@@ -40,7 +43,7 @@ macro_rules! define_region_property {
 pub use define_region_property;
 
 fn create_region(context: &mut Context) -> RegionId {
-    let data_container = context.get_data_container_mut::<RegionsPlugin>();
+    let data_container = context.get_or_init_data_container::<RegionsPlugin>();
     let region_id = match data_container.max_region_id {
         None => RegionId::new(0),
         Some(max_region_id) => RegionId::new(max_region_id.id + 1),
@@ -61,8 +64,8 @@ impl<'a> RegionPropertiesCreationBuilder<'a> for CreationBuilder<'a, RegionId> {
         mut self,
         value: T::Value,
     ) -> CreationBuilder<'a, RegionId> {
-        self.add_callback(move |context, region_id: RegionId| {
-            let data_container = context.get_data_container_mut::<RegionsPlugin>();
+        self.add_initializer::<T>(move |context, region_id: RegionId| {
+            let data_container = context.get_or_init_data_container::<RegionsPlugin>();
             data_container
                 .region_property_container
                 .set_value::<T>(region_id.id, value);
@@ -74,8 +77,8 @@ impl<'a> RegionPropertiesCreationBuilder<'a> for CreationBuilder<'a, RegionId> {
 type RegionUpdateCallback = dyn Fn(&mut Context, PersonId, RegionId);
 struct RegionsDataContainer {
     max_region_id: Option<RegionId>,
-    // Maps person by PersonId to RegionId
-    region_map: Vec<RegionId>,
+    // Maps person by PersonId to RegionId, None once the person is removed
+    region_map: Vec<Option<RegionId>>,
     region_property_container: VecDataContainer,
     region_change_callbacks: Vec<Rc<RegionUpdateCallback>>,
     partition_update_callback_providers: HashMap<TypeId, Box<PartitionUpdateCallbackProvider>>,
@@ -93,8 +96,63 @@ crate::context::define_plugin!(
     }
 );
 
+#[derive(Serialize, Deserialize)]
+struct RegionsCheckpointState {
+    max_region_id: Option<RegionId>,
+    region_map: Vec<Option<RegionId>>,
+}
+
+impl CheckpointPlugin for RegionsPlugin {
+    type State = RegionsCheckpointState;
+
+    fn save_state(data_container: &RegionsDataContainer) -> Self::State {
+        RegionsCheckpointState {
+            max_region_id: data_container.max_region_id,
+            region_map: data_container.region_map.clone(),
+        }
+    }
+
+    fn load_state(data_container: &mut RegionsDataContainer, state: Self::State) {
+        data_container.max_region_id = state.max_region_id;
+        data_container.region_map = state.region_map;
+    }
+}
+
+pub(crate) fn get_checkpoint_section() -> CheckpointSection {
+    CheckpointSection::for_plugin::<RegionsPlugin>()
+}
+
+// Returns the checkpoint sections needed to save every stored region
+// property
+pub(crate) fn get_required_checkpoint_sections(context: &Context) -> Vec<&'static str> {
+    match context.get_data_container::<RegionsPlugin>() {
+        None => Vec::new(),
+        Some(data_container) => data_container
+            .region_property_container
+            .get_property_names()
+            .collect(),
+    }
+}
+
+// Drops a removed person from their region
+pub(crate) fn remove_person_from_region(context: &mut Context, person_id: PersonId) {
+    let Ok(data_container) = context.try_get_data_container_mut::<RegionsPlugin>() else {
+        return;
+    };
+    if let Some(region_id) = data_container.region_map.get_mut(person_id.id) {
+        *region_id = None;
+    }
+}
+
+// Makes room for the regions of len people without reallocating
+pub(crate) fn reserve_person_regions(context: &mut Context, len: usize) {
+    let data_container = context.get_or_init_data_container::<RegionsPlugin>();
+    let additional = len.saturating_sub(data_container.region_map.len());
+    data_container.region_map.reserve(additional);
+}
+
 pub trait RegionsContext {
-    fn add_region(&mut self) -> CreationBuilder<RegionId>;
+    fn add_region(&mut self) -> CreationBuilder<'_, RegionId>;
 
     fn get_maximum_region_id(&self) -> Option<RegionId>;
 
@@ -102,6 +160,12 @@ pub trait RegionsContext {
 
     fn set_person_region(&mut self, person_id: PersonId, region_id: RegionId);
 
+    // Counts the people currently in the region, excluding removed people
+    fn get_region_population(&self, region_id: RegionId) -> usize;
+
+    // Requires a region to be set when each person is created
+    fn require_person_region(&mut self);
+
     fn get_region_property_value<T: RegionProperty>(&self, region_id: RegionId) -> T::Value;
 
     fn set_region_property_value<T: RegionProperty>(
@@ -121,10 +185,14 @@ pub trait RegionsContext {
     );
 
     fn remove_region_partition_callback<K: Partition>(&mut self);
+
+    fn register_checkpoint_region_property<T: RegionProperty>(&mut self)
+    where
+        T::Value: Serialize + DeserializeOwned;
 }
 
 impl RegionsContext for Context {
-    fn add_region(&mut self) -> CreationBuilder<RegionId> {
+    fn add_region(&mut self) -> CreationBuilder<'_, RegionId> {
         CreationBuilder::new(self, create_region, |_context, _region_id| {})
     }
 
@@ -137,16 +205,31 @@ impl RegionsContext for Context {
     }
 
     fn get_person_region(&self, person_id: PersonId) -> RegionId {
+        check_person_not_removed(self, person_id);
         let data_container = self
-            .get_data_container::<RegionsPlugin>()
-            .expect("Regions plugin hasn't been loaded.");
-        *data_container
+            .try_get_data_container::<RegionsPlugin>()
+            .unwrap_or_else(|e| panic!("{}", e));
+        data_container
             .region_map
             .get(person_id.id)
+            .copied()
+            .flatten()
             .expect("Person hasn't been assigned a region")
     }
 
+    fn get_region_population(&self, region_id: RegionId) -> usize {
+        match self.get_data_container::<RegionsPlugin>() {
+            None => 0,
+            Some(data_container) => data_container
+                .region_map
+                .iter()
+                .filter(|person_region_id| **person_region_id == Some(region_id))
+                .count(),
+        }
+    }
+
     fn set_person_region(&mut self, person_id: PersonId, region_id: RegionId) {
+        check_person_not_removed(self, person_id);
         let mut observation_callbacks = Vec::<Box<dyn Fn(&mut Context) + 'static>>::new();
         let mut partition_callbacks = Vec::new();
         // If data container is not loaded then there are no observers
@@ -156,7 +239,8 @@ impl RegionsContext for Context {
             }
             // Observation callbacks
             if !data_container.region_change_callbacks.is_empty() {
-                let current_region_id = data_container.region_map[person_id.id];
+                let current_region_id = data_container.region_map[person_id.id]
+                    .expect("Person hasn't been assigned a region");
                 for callback in &data_container.region_change_callbacks {
                     let internal_callback = Rc::clone(callback);
                     observation_callbacks.push(Box::new(move |context| {
@@ -177,8 +261,8 @@ impl RegionsContext for Context {
         }
 
         // Update value
-        let data_container = self.get_data_container_mut::<RegionsPlugin>();
-        data_container.region_map[person_id.id] = region_id;
+        let data_container = self.get_or_init_data_container::<RegionsPlugin>();
+        data_container.region_map[person_id.id] = Some(region_id);
 
         // Update partitions
         for partition_callback in partition_callbacks {
@@ -186,6 +270,10 @@ impl RegionsContext for Context {
         }
     }
 
+    fn require_person_region(&mut self) {
+        self.require_initializer::<PersonId, RegionId>();
+    }
+
     fn get_region_property_value<T: RegionProperty>(&self, region_id: RegionId) -> T::Value {
         let data_container = self.get_data_container::<RegionsPlugin>();
         match data_container {
@@ -201,7 +289,7 @@ impl RegionsContext for Context {
         region_id: RegionId,
         value: T::Value,
     ) {
-        let data_container = self.get_data_container_mut::<RegionsPlugin>();
+        let data_container = self.get_or_init_data_container::<RegionsPlugin>();
         data_container
             .region_property_container
             .set_value::<T>(region_id.id, value);
@@ -211,7 +299,7 @@ impl RegionsContext for Context {
         &mut self,
         callback: impl Fn(&mut Context, PersonId, RegionId) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<RegionsPlugin>();
+        let data_container = self.get_or_init_data_container::<RegionsPlugin>();
         data_container
             .region_change_callbacks
             .push(Rc::new(callback));
@@ -221,18 +309,41 @@ impl RegionsContext for Context {
         &mut self,
         provider: impl (Fn(&Context, PersonId) -> Box<dyn Fn(&mut Context)>) + 'static,
     ) {
-        let data_container = self.get_data_container_mut::<RegionsPlugin>();
+        let data_container = self.get_or_init_data_container::<RegionsPlugin>();
         data_container
             .partition_update_callback_providers
             .insert(TypeId::of::<K>(), Box::new(provider));
     }
 
     fn remove_region_partition_callback<K: Partition>(&mut self) {
-        let data_container = self.get_data_container_mut::<RegionsPlugin>();
+        let data_container = self.get_or_init_data_container::<RegionsPlugin>();
         data_container
             .partition_update_callback_providers
             .remove(&TypeId::of::<K>());
     }
+
+    fn register_checkpoint_region_property<T: RegionProperty>(&mut self)
+    where
+        T::Value: Serialize + DeserializeOwned,
+    {
+        self.add_checkpoint_section(
+            type_name::<T>(),
+            |context| match context.get_data_container::<RegionsPlugin>() {
+                None => Vec::new(),
+                Some(data_container) => data_container
+                    .region_property_container
+                    .get_values::<T>()
+                    .cloned()
+                    .unwrap_or_default(),
+            },
+            |context, values: Vec<T::Value>| {
+                let data_container = context.get_or_init_data_container::<RegionsPlugin>();
+                data_container
+                    .region_property_container
+                    .set_values::<T>(values);
+            },
+        );
+    }
 }
 
 pub trait RegionsPersonBuilder<'a> {
@@ -241,13 +352,39 @@ pub trait RegionsPersonBuilder<'a> {
 
 impl<'a> RegionsPersonBuilder<'a> for PersonBuilder<'a> {
     fn set_region(mut self, region_id: RegionId) -> PersonBuilder<'a> {
-        // TODO: Validation to require that this method be called (because a region must be specified)
-        self.add_callback(move |context, person_id: PersonId| {
-            let data_container = context.get_data_container_mut::<RegionsPlugin>();
+        self.add_initializer::<RegionId>(move |context, person_id: PersonId| {
+            let data_container = context.get_or_init_data_container::<RegionsPlugin>();
             if person_id.id != data_container.region_map.len() {
                 panic!("Expecting sequential person ids");
             }
-            data_container.region_map.push(region_id);
+            data_container.region_map.push(Some(region_id));
+        });
+        self
+    }
+}
+
+pub trait RegionsPeopleBatchBuilder<'a> {
+    // Sets the region of each person in the batch, in id order
+    fn set_regions(self, region_ids: Vec<RegionId>) -> PeopleBatchBuilder<'a>;
+}
+
+impl<'a> RegionsPeopleBatchBuilder<'a> for PeopleBatchBuilder<'a> {
+    fn set_regions(mut self, region_ids: Vec<RegionId>) -> PeopleBatchBuilder<'a> {
+        if region_ids.len() != self.get_count() {
+            panic!(
+                "Expected {} regions but got {}",
+                self.get_count(),
+                region_ids.len()
+            );
+        }
+        self.add_initializer::<RegionId>(move |context, range| {
+            let data_container = context.get_or_init_data_container::<RegionsPlugin>();
+            if range.start.id != data_container.region_map.len() {
+                panic!("Expecting sequential person ids");
+            }
+            data_container
+                .region_map
+                .extend(region_ids.into_iter().map(Some));
         });
         self
     }
@@ -270,14 +407,15 @@ impl<'a, P: Partition> RegionsPartitionBuilder<'a, P> for PartitionBuilder<'a, P
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod test {
     use crate::context::{Component, Context};
     use crate::data_containers::PersonContainer;
     use crate::partitions::{Partition, PartitionContext};
-    use crate::people::PeopleContext;
+    use crate::people::{PeopleContext, PersonId};
     use crate::regions::{
         RegionId, RegionPropertiesCreationBuilder, RegionsContext, RegionsPartitionBuilder,
-        RegionsPersonBuilder,
+        RegionsPeopleBatchBuilder, RegionsPersonBuilder,
     };
 
     define_region_property!(RegionPropertyA, f64, 0.0);
@@ -289,7 +427,7 @@ mod test {
         assert_eq!(context.get_maximum_region_id(), None);
 
         // Add some regions
-        let region_zero = context.add_region().execute();
+        let region_zero = context.add_region().execute().unwrap();
         assert_eq!(context.get_maximum_region_id(), Some(region_zero));
         assert_eq!(
             context.get_region_property_value::<RegionPropertyA>(region_zero),
@@ -299,17 +437,26 @@ mod test {
         let region_one = context
             .add_region()
             .set_region_property::<RegionPropertyA>(my_float)
-            .execute();
+            .execute()
+            .unwrap();
         assert_eq!(
             context.get_region_property_value::<RegionPropertyA>(region_one),
             my_float
         );
 
         // Add some people
-        let new_person = context.add_person().set_region(region_zero).execute();
+        let new_person = context
+            .add_person()
+            .set_region(region_zero)
+            .execute()
+            .unwrap();
         assert_eq!(context.get_maximum_person_id(), Some(new_person));
         assert_eq!(context.get_person_region(new_person), region_zero);
-        let new_person = context.add_person().set_region(region_one).execute();
+        let new_person = context
+            .add_person()
+            .set_region(region_one)
+            .execute()
+            .unwrap();
         assert_eq!(context.get_maximum_person_id(), Some(new_person));
         assert_eq!(context.get_person_region(new_person), region_one);
     }
@@ -342,8 +489,12 @@ mod test {
             .add_region_sensitivity()
             .execute();
 
-        let region_zero = context.add_region().execute();
-        let person_id = context.add_person().set_region(region_zero).execute();
+        let region_zero = context.add_region().execute().unwrap();
+        let person_id = context
+            .add_person()
+            .set_region(region_zero)
+            .execute()
+            .unwrap();
         context.execute();
         assert!(context.get_data_container_mut::<ComponentOne>().is_none());
         let cell = context.get_partition_cell::<PartitionOneKey>(region_zero);
@@ -351,7 +502,7 @@ mod test {
         assert_eq!(cell.unwrap().len(), 1);
         assert!(cell.unwrap().contains(&person_id));
 
-        let new_region = context.add_region().execute();
+        let new_region = context.add_region().execute().unwrap();
         context.set_person_region(person_id, new_region);
         context.execute();
         assert_eq!(
@@ -374,4 +525,57 @@ mod test {
         assert_eq!(cell.unwrap().len(), 1);
         assert!(cell.unwrap().contains(&person_id));
     }
+
+    #[test]
+    fn test_require_person_region() {
+        let mut context = Context::new();
+        context.require_person_region();
+        let region_id = context.add_region().execute().unwrap();
+        let error = context.add_person().execute().unwrap_err();
+        assert_eq!(
+            error.missing_initializers,
+            vec![std::any::type_name::<RegionId>()]
+        );
+        // No person is created when an initializer is missing
+        assert_eq!(context.get_maximum_person_id(), None);
+        let person_id = context
+            .add_person()
+            .set_region(region_id)
+            .execute()
+            .unwrap();
+        assert_eq!(context.get_person_region(person_id), region_id);
+
+        // The requirement also applies to batches
+        assert!(context.add_people(2).execute().is_err());
+        let range = context
+            .add_people(2)
+            .set_regions(vec![region_id, region_id])
+            .execute()
+            .unwrap();
+        assert_eq!(context.get_person_region(range.start), region_id);
+        assert_eq!(context.get_population(), 3);
+    }
+
+    #[test]
+    fn test_region_population_after_removal() {
+        let mut context = Context::new();
+        let region_zero = context.add_region().execute().unwrap();
+        let region_one = context.add_region().execute().unwrap();
+        assert_eq!(context.get_region_population(region_zero), 0);
+        let range = context
+            .add_people(3)
+            .set_regions(vec![region_zero, region_zero, region_one])
+            .execute()
+            .unwrap();
+        assert_eq!(context.get_region_population(region_zero), 2);
+        assert_eq!(context.get_region_population(region_one), 1);
+
+        context.remove_person(range.start);
+        assert_eq!(context.get_region_population(region_zero), 1);
+        assert_eq!(context.get_region_population(region_one), 1);
+
+        context.set_person_region(PersonId::new(1), region_one);
+        assert_eq!(context.get_region_population(region_zero), 0);
+        assert_eq!(context.get_region_population(region_one), 2);
+    }
 }
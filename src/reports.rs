@@ -48,7 +48,7 @@ pub trait ReportsContext {
 
 impl ReportsContext for Context {
     fn set_report_item_handler<T: Report>(&mut self, callback: impl FnMut(T::Item) + 'static) {
-        let data_container = self.get_data_container_mut::<ReportsPlugin>();
+        let data_container = self.get_or_init_data_container::<ReportsPlugin>();
         data_container.report_item_handlers.insert(
             TypeId::of::<T>(),
             Box::new(ReportItemHandler::new(callback)),
@@ -56,7 +56,7 @@ impl ReportsContext for Context {
     }
 
     fn release_report_item<T: Report>(&mut self, item: T::Item) {
-        let data_container = self.get_data_container_mut::<ReportsPlugin>();
+        let data_container = self.get_or_init_data_container::<ReportsPlugin>();
         match data_container
             .report_item_handlers
             .get_mut(&TypeId::of::<T>())
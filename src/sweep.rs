@@ -0,0 +1,178 @@
+use crate::context::Context;
+use crate::global_properties::{GlobalPropertyContext, GlobalPropertyLoadError};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// Global property values for one scenario, keyed by registered name
+pub type Scenario = Map<String, Value>;
+
+// A set of scenarios. Every scenario in the list is combined with every
+// point in the grid, and each one starts from the base values.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Sweep {
+    #[serde(default)]
+    base: Scenario,
+    #[serde(default)]
+    grid: BTreeMap<String, Vec<Value>>,
+    #[serde(default)]
+    scenarios: Vec<Scenario>,
+}
+
+impl Sweep {
+    pub fn new() -> Sweep {
+        Sweep::default()
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Sweep, GlobalPropertyLoadError> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn set_base_value(mut self, name: &str, value: Value) -> Sweep {
+        self.base.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn add_grid_values(mut self, name: &str, values: Vec<Value>) -> Sweep {
+        self.grid.insert(name.to_string(), values);
+        self
+    }
+
+    pub fn add_scenario(mut self, scenario: Scenario) -> Sweep {
+        self.scenarios.push(scenario);
+        self
+    }
+
+    pub fn get_scenarios(&self) -> Vec<Scenario> {
+        let mut grid_points = vec![Scenario::new()];
+        for (name, values) in &self.grid {
+            grid_points = grid_points
+                .iter()
+                .flat_map(|grid_point| {
+                    values.iter().map(move |value| {
+                        let mut grid_point = grid_point.clone();
+                        grid_point.insert(name.clone(), value.clone());
+                        grid_point
+                    })
+                })
+                .collect();
+        }
+
+        let listed_scenarios = if self.scenarios.is_empty() {
+            vec![Scenario::new()]
+        } else {
+            self.scenarios.clone()
+        };
+        let mut scenarios = Vec::new();
+        for listed_scenario in &listed_scenarios {
+            for grid_point in &grid_points {
+                let mut scenario = self.base.clone();
+                scenario.extend(listed_scenario.clone());
+                scenario.extend(grid_point.clone());
+                scenarios.push(scenario);
+            }
+        }
+        scenarios
+    }
+
+    // Builds a Context for each scenario. Setup is called first and must
+    // register the global properties named in the sweep.
+    pub fn build_contexts(
+        &self,
+        setup: impl Fn(&mut Context),
+    ) -> Result<Vec<Context>, GlobalPropertyLoadError> {
+        let mut contexts = Vec::new();
+        for scenario in self.get_scenarios() {
+            let mut context = Context::new();
+            setup(&mut context);
+            context.set_global_properties(&scenario)?;
+            contexts.push(context);
+        }
+        Ok(contexts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::context::Context;
+    use crate::global_properties::{GlobalPropertyContext, GlobalPropertyLoadError};
+    use crate::sweep::Sweep;
+    use serde_json::json;
+
+    crate::define_global_property!(R0, f64);
+    crate::define_global_property!(InfectiousPeriod, usize);
+    crate::define_global_property!(SchoolClosure, bool);
+
+    fn setup(context: &mut Context) {
+        context.register_global_property::<R0>("r0");
+        context.register_global_property::<InfectiousPeriod>("infectious_period");
+        context.register_global_property::<SchoolClosure>("school_closure");
+    }
+
+    #[test]
+    fn test_grid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sweep.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "base": {"school_closure": false},
+                "grid": {"r0": [1.5, 2.0, 2.5], "infectious_period": [5, 7]}
+            }"#,
+        )
+        .unwrap();
+        let sweep = Sweep::from_file(&path).unwrap();
+        let contexts = sweep.build_contexts(setup).unwrap();
+        assert_eq!(contexts.len(), 6);
+        let mut values: Vec<(usize, f64)> = contexts
+            .iter()
+            .map(|context| {
+                assert!(!*context
+                    .get_global_property_value::<SchoolClosure>()
+                    .unwrap());
+                (
+                    *context
+                        .get_global_property_value::<InfectiousPeriod>()
+                        .unwrap(),
+                    *context.get_global_property_value::<R0>().unwrap(),
+                )
+            })
+            .collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        values.dedup();
+        assert_eq!(values.len(), 6);
+        assert_eq!(values[0], (5, 1.5));
+        assert_eq!(values[5], (7, 2.5));
+    }
+
+    #[test]
+    fn test_scenario_list() {
+        let sweep = Sweep::new()
+            .set_base_value("r0", json!(2.0))
+            .set_base_value("infectious_period", json!(7))
+            .set_base_value("school_closure", json!(false))
+            .add_scenario(json!({}).as_object().unwrap().clone())
+            .add_scenario(json!({"school_closure": true}).as_object().unwrap().clone())
+            .add_grid_values("r0", vec![json!(1.5), json!(2.5)]);
+        let scenarios = sweep.get_scenarios();
+        assert_eq!(scenarios.len(), 4);
+        assert_eq!(scenarios[0]["r0"], json!(1.5));
+        assert_eq!(scenarios[0]["school_closure"], json!(false));
+        assert_eq!(scenarios[3]["r0"], json!(2.5));
+        assert_eq!(scenarios[3]["school_closure"], json!(true));
+    }
+
+    #[test]
+    fn test_unknown_property() {
+        let sweep = Sweep::new().add_grid_values("beta", vec![json!(0.1)]);
+        assert!(matches!(
+            sweep.build_contexts(setup),
+            Err(GlobalPropertyLoadError::UnknownProperties(_))
+        ));
+    }
+}